@@ -1,20 +1,74 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-
-pub fn sorted_calories_from_file(path: &str) -> Vec<usize> {
-    let fd = File::open(path).unwrap();
-    let reader = BufReader::new(fd);
-    let mut tallies = vec![0];
-    let mut index = 0;
-    for line in reader.lines() {
-        let line = line.unwrap();
-        if line.trim() == "" {
-            index += 1;
-            tallies.push(0);
-        } else {
-            tallies[index] += line.parse::<usize>().unwrap();
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::BufRead;
+
+use aoc::paragraphs;
+
+/// The `k` elves that carry the most calories as `(elf_index, calories)`, the most calories first
+pub fn top_calories_from_file(path: &str, k: usize) -> Vec<(usize, usize)> {
+    top_calories(paragraphs::from_file(path), k)
+}
+
+fn top_calories<R: BufRead>(blocks: paragraphs::Paragraphs<R>, k: usize) -> Vec<(usize, usize)> {
+    let tallies = blocks.map(|block| {
+        block
+            .iter()
+            .map(|line| line.trim().parse::<usize>().unwrap())
+            .sum()
+    });
+    top_k(tallies.enumerate(), k)
+}
+
+/// Keep the `k` largest values in a min-heap instead of sorting all of them. On ties the earlier
+/// index wins.
+pub fn top_k<I: IntoIterator<Item = (usize, usize)>>(items: I, k: usize) -> Vec<(usize, usize)> {
+    let mut heap = BinaryHeap::with_capacity(k + 1);
+    for (index, value) in items {
+        heap.push(Reverse((value, Reverse(index))));
+        if heap.len() > k {
+            heap.pop();
         }
     }
-    tallies.sort();
-    tallies
+    heap.into_sorted_vec()
+        .into_iter()
+        .map(|Reverse((value, Reverse(index)))| (index, value))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "1000\n2000\n3000\n\n4000\n\n5000\n6000\n\n7000\n8000\n9000\n\n10000\n";
+
+    fn top(input: &str, k: usize) -> Vec<(usize, usize)> {
+        top_calories(paragraphs::paragraphs(input.as_bytes()), k)
+    }
+
+    #[test]
+    fn sample() {
+        assert_eq!(top(SAMPLE, 1), [(3, 24000)]);
+        assert_eq!(top(SAMPLE, 3), [(3, 24000), (2, 11000), (4, 10000)]);
+        // trailing blank lines don't add an elf without calories
+        assert_eq!(top(&format!("{SAMPLE}\n\n"), 10).len(), 5);
+        assert_eq!(top("1\r\n\r\n2\r\n3\r\n\r\n", 10), [(1, 5), (0, 1)]);
+    }
+
+    #[test]
+    fn ties_go_to_the_earlier_elf() {
+        let items = [(0, 5), (1, 7), (2, 5), (3, 7), (4, 5)];
+        assert_eq!(top_k(items, 1), [(1, 7)]);
+        assert_eq!(top_k(items, 3), [(1, 7), (3, 7), (0, 5)]);
+        assert_eq!(top_k(items, 4), [(1, 7), (3, 7), (0, 5), (2, 5)]);
+        // the order of the input doesn't matter
+        assert_eq!(top_k(items.into_iter().rev(), 3), [(1, 7), (3, 7), (0, 5)]);
+    }
+
+    #[test]
+    fn more_or_fewer_elves_than_k() {
+        let items = [(0, 3), (1, 1), (2, 2)];
+        assert_eq!(top_k(items, 10), [(0, 3), (2, 2), (1, 1)]);
+        assert!(top_k(items, 0).is_empty());
+        assert!(top_k([], 3).is_empty());
+    }
 }
@@ -1,69 +1,107 @@
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
+use aoc::ocr::{OcrError, SMALL};
+use aoc::vm::{Flow, Instruction, Machine, Observer, Registers, Value};
+
+const X: usize = 0;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Command {
     Noop,
-    Addx(isize),
+    Addx(Value),
+}
+
+impl Instruction<1> for Command {
+    fn cycles(&self) -> usize {
+        match self {
+            Command::Noop => 1,
+            Command::Addx(_) => 2,
+        }
+    }
+
+    fn execute(&self, registers: &mut Registers<1>) -> Flow {
+        if let Command::Addx(v) = self {
+            registers[X] += v;
+        }
+        Flow::Next
+    }
 }
 
 pub struct CPU {
-    cycle: usize,
-    x: isize,
-    queue: Vec<Command>,
-    framebuffer: Vec<bool>,
+    machine: Machine<Command, 1>,
 }
 
 impl CPU {
     pub fn new(commands: Vec<Command>) -> Self {
         Self {
-            cycle: 0,
-            x: 1,
-            queue: commands,
-            framebuffer: Vec::with_capacity(40 * 6),
+            machine: Machine::new(commands, Registers::from([1])),
         }
     }
 
-    pub fn run(&mut self, checkpoints: &[usize]) -> Vec<isize> {
-        let mut result = vec![];
-        let mut iter = checkpoints.iter();
-        let mut checkpoint = iter.next().cloned().unwrap();
-        for command in &self.queue {
-            let cycles = match command {
-                Command::Noop => 1,
-                Command::Addx(_) => 2,
-            };
-            for _ in 0..cycles {
-                self.cycle += 1;
-                self.framebuffer.push(
-                    self.x.is_positive()
-                        && ((self.x as usize) % 40).abs_diff((self.cycle - 1) % 40) <= 1,
-                );
-
-                if self.cycle == checkpoint {
-                    result.push(self.cycle as isize * self.x);
-                    if let Some(v) = iter.next() {
-                        checkpoint = *v;
-                    } else {
-                        checkpoint = 0; // impossible
-                    }
-                }
-            }
-            if let Command::Addx(v) = command {
-                self.x += v;
-            }
+    pub fn run<O: Observer<1>>(&mut self, observer: &mut O) {
+        self.machine.run(observer);
+    }
+}
+
+/// sums up the signal strength (cycle * x) during the checkpoint cycles
+pub struct SignalStrength {
+    checkpoints: Vec<usize>,
+    pub total: Value,
+}
+
+impl SignalStrength {
+    pub fn new(checkpoints: &[usize]) -> Self {
+        Self {
+            checkpoints: checkpoints.to_vec(),
+            total: 0,
+        }
+    }
+}
+
+impl Observer<1> for SignalStrength {
+    fn on_cycle(&mut self, cycle: usize, registers: &Registers<1>) {
+        if self.checkpoints.contains(&cycle) {
+            self.total += cycle as Value * registers[X];
         }
-        println!("final cycle: {}", self.cycle);
-        result
     }
+}
+
+/// the 40x6 CRT that draws a pixel per cycle wherever the sprite at x is visible
+pub struct Screen {
+    framebuffer: Vec<bool>,
+}
 
-    pub fn render_screen(&self) {
+impl Default for Screen {
+    fn default() -> Self {
+        Self {
+            framebuffer: Vec::with_capacity(40 * 6),
+        }
+    }
+}
+
+impl Observer<1> for Screen {
+    fn on_cycle(&mut self, cycle: usize, registers: &Registers<1>) {
+        // the sprite is 3 pixels wide and can be partially or entirely off the screen
+        let column = ((cycle - 1) % 40) as Value;
+        self.framebuffer.push((registers[X] - column).abs() <= 1);
+    }
+}
+
+impl Screen {
+    pub fn read_text(&self) -> Result<String, OcrError> {
+        SMALL.decode(&self.framebuffer, 40)
+    }
+
+    pub fn render(&self) -> String {
+        let mut result = String::with_capacity(41 * 6);
         for (i, v) in self.framebuffer.iter().enumerate() {
-            print!("{}", if *v { '#' } else { '.' });
+            result.push(if *v { '#' } else { '.' });
             if (i + 1) % 40 == 0 {
-                println!("");
+                result.push('\n');
             }
         }
+        result
     }
 }
 
@@ -82,3 +120,67 @@ pub fn read_commands_from_file(path: &str) -> Vec<Command> {
     }
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: [&str; 6] = [
+        ".##..###...##..####.####..##..#..#..###.",
+        "#..#.#..#.#..#.#....#....#..#.#..#...#..",
+        "#..#.###..#....###..###..#....####...#..",
+        "####.#..#.#....#....#....#.##.#..#...#..",
+        "#..#.#..#.#..#.#....#....#..#.#..#...#..",
+        "#..#.###...##..####.#.....###.#..#..###.",
+    ];
+
+    /// Feed the screen a value of x for every pixel that lights it up or keeps it dark, using
+    /// sprite positions that are partially or entirely off the screen as well
+    fn draw(rows: &[&str]) -> Screen {
+        let mut screen = Screen::default();
+        let pixels = rows.iter().flat_map(|row| row.chars());
+        for (i, pixel) in pixels.enumerate() {
+            let column = (i % 40) as Value;
+            let x = if pixel == '#' {
+                column + [-1, 0, 1][i % 3]
+            } else {
+                column + [-2, 2, 40, -40][i % 4]
+            };
+            screen.on_cycle(i + 1, &Registers::from([x]));
+        }
+        screen
+    }
+
+    #[test]
+    fn lights_pixels_at_the_edges() {
+        let screen = draw(&TEXT);
+        assert_eq!(screen.render(), TEXT.join("\n") + "\n");
+        assert_eq!(screen.read_text().unwrap(), "ABCEFGHI");
+    }
+
+    #[test]
+    fn sprite_outside_the_screen() {
+        let mut screen = Screen::default();
+        for (cycle, x) in [(1, -1), (2, -1), (3, 41), (40, 40), (41, 0), (42, 0)] {
+            screen.on_cycle(cycle, &Registers::from([x]));
+        }
+        assert_eq!(screen.framebuffer, [true, false, false, true, true, true]);
+    }
+
+    #[test]
+    fn reports_unknown_glyphs() {
+        let mut rows = TEXT;
+        rows[0] = "###..###...##..####.####..##..#..#..###.";
+        let err = draw(&rows).read_text().unwrap_err();
+        assert!(matches!(err, OcrError::UnknownGlyph { column: 0, .. }));
+    }
+
+    #[test]
+    fn signal_strength_at_the_checkpoints() {
+        let program = vec![Command::Noop, Command::Addx(3), Command::Addx(-5)];
+        let mut cpu = CPU::new(program);
+        let mut signal = SignalStrength::new(&[2, 4, 5]);
+        cpu.run(&mut signal);
+        assert_eq!(signal.total, 2 + 4 * 4 + 5 * 4);
+    }
+}
@@ -1,100 +1,241 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::fmt;
+use std::str::FromStr;
+
+use aoc::paragraphs::{self, FromBlock};
 
 pub type Crate = char;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Crane {
+    /// moves one crate at a time
+    CrateMover9000,
+    /// moves all crates at once, keeping their order
+    CrateMover9001,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Warehouse {
     stacks: Vec<Vec<Crate>>,
 }
 
 impl Warehouse {
-    pub(crate) fn apply(&mut self, instruction: &MoveInstruction, all_at_once: bool) {
-        if all_at_once {
-            let source = &mut self.stacks[instruction.from];
-            let intermediate = source.split_off(source.len() - instruction.amount);
-            self.stacks[instruction.to].extend(intermediate);
-        } else {
-            for _ in 0..instruction.amount {
-                let value = self.stacks[instruction.from].pop().unwrap();
-                self.stacks[instruction.to].push(value);
+    pub fn apply(&mut self, instruction: &MoveInstruction, crane: Crane) {
+        match crane {
+            Crane::CrateMover9001 => {
+                let source = &mut self.stacks[instruction.from];
+                let intermediate = source.split_off(source.len() - instruction.amount);
+                self.stacks[instruction.to].extend(intermediate);
+            }
+            Crane::CrateMover9000 => {
+                for _ in 0..instruction.amount {
+                    let value = self.stacks[instruction.from].pop().unwrap();
+                    self.stacks[instruction.to].push(value);
+                }
             }
         }
     }
 
-    pub(crate) fn report_top_row(&self) {
-        let mut result = String::with_capacity(10);
-        for stack in &self.stacks {
-            result.push(*stack.last().unwrap())
+    /// revert an instruction that has been applied with the same crane
+    pub fn undo(&mut self, instruction: &MoveInstruction, crane: Crane) {
+        self.apply(&instruction.reversed(), crane);
+    }
+
+    /// apply all instructions and call `on_step` with the state after each of them
+    pub fn run<F: FnMut(&Warehouse)>(
+        &mut self,
+        instructions: &[MoveInstruction],
+        crane: Crane,
+        mut on_step: F,
+    ) {
+        for instruction in instructions {
+            self.apply(instruction, crane);
+            on_step(self);
+        }
+    }
+
+    /// undo all instructions, starting from the last one
+    pub fn rewind<F: FnMut(&Warehouse)>(
+        &mut self,
+        instructions: &[MoveInstruction],
+        crane: Crane,
+        mut on_step: F,
+    ) {
+        for instruction in instructions.iter().rev() {
+            self.undo(instruction, crane);
+            on_step(self);
         }
-        println!("top row: {}", result);
+    }
+
+    /// the topmost crate of every stack, a space for empty stacks
+    pub fn top_row(&self) -> String {
+        self.stacks
+            .iter()
+            .map(|stack| stack.last().copied().unwrap_or(' '))
+            .collect()
+    }
+}
+
+/// renders the warehouse in the same drawing format as the puzzle input
+impl fmt::Display for Warehouse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let height = self.stacks.iter().map(Vec::len).max().unwrap_or(0);
+        for level in (0..height).rev() {
+            let row: Vec<String> = self
+                .stacks
+                .iter()
+                .map(|stack| match stack.get(level) {
+                    Some(name) => format!("[{name}]"),
+                    None => String::from("   "),
+                })
+                .collect();
+            writeln!(f, "{}", row.join(" "))?;
+        }
+        let labels: Vec<String> = (1..=self.stacks.len()).map(|i| format!("{i:^3}")).collect();
+        writeln!(f, "{}", labels.join(" "))
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct MoveInstruction {
     pub amount: usize,
     pub from: usize,
     pub to: usize,
 }
 
-pub fn read_stacks_from_file(path: &str) -> Warehouse {
-    let mut stacks = vec![];
-    for _ in 0..9 {
-        stacks.push(Vec::new());
-    }
-    let fd = File::open(path).unwrap();
-    let reader = BufReader::new(fd);
-    for line in reader.lines() {
-        let line = line.unwrap();
-        let line = line.trim_end();
-        if line.starts_with(" 1 ") {
-            break;
+impl MoveInstruction {
+    pub fn reversed(&self) -> Self {
+        Self {
+            amount: self.amount,
+            from: self.to,
+            to: self.from,
         }
+    }
+}
+
+/// The drawing of the stacks. The number of stacks is taken from the label line at the bottom.
+impl FromBlock for Warehouse {
+    type Err = String;
 
-        let mut iter = line.chars();
-        for i in 0..10 {
-            if let Some(left_brace) = iter.next() {
-                if left_brace != '[' {
-                    iter.next(); // iter.skip() takes ownership and that causes issues
-                    iter.next();
-                    iter.next();
-                    continue;
+    fn from_block(lines: &[String]) -> Result<Self, String> {
+        let (labels, drawing) = lines.split_last().ok_or("empty drawing")?;
+        let count = labels.split_whitespace().count();
+        let mut stacks = vec![Vec::new(); count];
+        for line in drawing.iter().rev() {
+            // every crate takes up 4 characters: "[X] "
+            for (i, stack) in stacks.iter_mut().enumerate() {
+                match line.as_bytes().get(i * 4..i * 4 + 3) {
+                    Some([b'[', name, b']']) => stack.push(*name as Crate),
+                    Some(b"   ") | None => (),
+                    Some(other) => {
+                        return Err(format!(
+                            "unexpected crate {:?}",
+                            String::from_utf8_lossy(other)
+                        ))
+                    }
                 }
-            } else {
-                break;
             }
-            let name = &iter.next().unwrap();
-            stacks[i].push(*name);
-            let right_brace = &iter.next().unwrap();
-            assert_eq!(*right_brace, ']');
-            if let Some(space) = &iter.next() {
-                assert_eq!(*space, ' ');
-            } // else: EOL
         }
+        Ok(Warehouse { stacks })
     }
-    for stack in &mut stacks {
-        stack.reverse();
+}
+
+/// lines like `move 1 from 2 to 1`
+impl FromStr for MoveInstruction {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = line.split(' ').collect();
+        let number = |i: usize| -> Result<usize, String> {
+            parts
+                .get(i)
+                .and_then(|part| part.parse().ok())
+                .ok_or_else(|| format!("bad instruction: {line}"))
+        };
+        Ok(MoveInstruction {
+            amount: number(1)?,
+            from: number(3)? - 1,
+            to: number(5)? - 1,
+        })
     }
-    Warehouse { stacks }
 }
 
-pub fn read_instructions_from_file(path: &str) -> Vec<MoveInstruction> {
-    let fd = File::open(path).unwrap();
-    let reader = BufReader::new(fd);
-    let mut search = true;
-    let mut result = Vec::new();
-    for line in reader.lines() {
-        if search {
-            if line.unwrap().trim() == "" {
-                search = false
-            }
-        } else {
-            let line = line.unwrap();
-            let parts: Vec<&str> = line.split(' ').collect();
-            let amount = parts[1].parse().unwrap();
-            let from = parts[3].parse::<usize>().unwrap() - 1;
-            let to = parts[5].parse::<usize>().unwrap() - 1;
-            result.push(MoveInstruction { amount, from, to });
+/// the drawing and the move instructions
+pub fn read_puzzle_from_file(path: &str) -> (Warehouse, Vec<MoveInstruction>) {
+    paragraphs::from_file(path)
+        .sections2()
+        .unwrap_or_else(|err| panic!("{}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DRAWING: &str = "    [D]    
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 
+";
+
+    const INSTRUCTIONS: &str = "move 1 from 2 to 1
+move 3 from 1 to 3
+move 2 from 2 to 1
+move 1 from 1 to 2";
+
+    fn sample() -> (Warehouse, Vec<MoveInstruction>) {
+        let input = format!("{DRAWING}\n{INSTRUCTIONS}\n");
+        paragraphs::paragraphs(input.as_bytes())
+            .sections2()
+            .unwrap()
+    }
+
+    #[test]
+    fn display_reproduces_the_drawing() {
+        let (warehouse, _) = sample();
+        assert_eq!(warehouse.to_string(), DRAWING);
+    }
+
+    #[test]
+    fn top_rows_of_the_sample() {
+        let (warehouse, instructions) = sample();
+        for (crane, expected) in [
+            (Crane::CrateMover9000, "CMZ"),
+            (Crane::CrateMover9001, "MCD"),
+        ] {
+            let mut warehouse = warehouse.clone();
+            let mut steps = 0;
+            warehouse.run(&instructions, crane, |_| steps += 1);
+            assert_eq!(steps, instructions.len());
+            assert_eq!(warehouse.top_row(), expected);
         }
     }
-    result
+
+    #[test]
+    fn undo_round_trips() {
+        let (original, instructions) = sample();
+        for crane in [Crane::CrateMover9000, Crane::CrateMover9001] {
+            let mut warehouse = original.clone();
+            let mut history = vec![warehouse.clone()];
+            warehouse.run(&instructions, crane, |state| history.push(state.clone()));
+            history.pop();
+            warehouse.rewind(&instructions, crane, |state| {
+                assert_eq!(Some(state), history.pop().as_ref());
+            });
+            assert_eq!(warehouse, original);
+            assert_eq!(warehouse.to_string(), DRAWING);
+        }
+    }
+
+    #[test]
+    fn reversed_swaps_the_stacks() {
+        let instruction: MoveInstruction = "move 2 from 1 to 3".parse().unwrap();
+        assert_eq!(
+            instruction.reversed(),
+            MoveInstruction {
+                amount: 2,
+                from: 2,
+                to: 0
+            }
+        );
+        assert_eq!(instruction.reversed().reversed(), instruction);
+    }
 }
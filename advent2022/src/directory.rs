@@ -1,101 +1,440 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::fmt::Write;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
-pub struct Directory {
-    files: HashMap<String, usize>,
-    directories: HashMap<String, Directory>,
+pub type NodeId = usize;
+
+const ROOT: NodeId = 0;
+
+#[derive(Debug)]
+enum Kind {
+    File,
+    /// children are sorted by name, so that listings are stable
+    Directory(BTreeMap<String, NodeId>),
+}
+
+#[derive(Debug)]
+struct Node {
+    name: String,
+    path: String,
+    /// `None` for the root and for removed nodes
+    parent: Option<NodeId>,
+    kind: Kind,
+    /// total size including all descendants
+    size: usize,
+}
+
+/// All nodes live in one arena and refer to each other by index. Sizes are kept up to date for
+/// every directory whenever something changes below it.
+#[derive(Debug)]
+pub struct FileSystem {
+    nodes: Vec<Node>,
+    cwd: NodeId,
+}
+
+#[derive(Debug)]
+pub struct TranscriptError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Default for FileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl Directory {
+impl FileSystem {
     pub fn new() -> Self {
+        let root = Node {
+            name: String::new(),
+            path: String::from("/"),
+            parent: None,
+            kind: Kind::Directory(BTreeMap::new()),
+            size: 0,
+        };
         Self {
-            files: HashMap::new(),
-            directories: HashMap::new(),
+            nodes: vec![root],
+            cwd: ROOT,
         }
     }
 
-    pub fn add_file(&mut self, name: String, size: usize) {
-        self.files.insert(name, size);
+    fn children(&self, id: NodeId) -> Option<&BTreeMap<String, NodeId>> {
+        match &self.nodes[id].kind {
+            Kind::Directory(children) => Some(children),
+            Kind::File => None,
+        }
+    }
+
+    fn children_mut(&mut self, id: NodeId) -> Option<&mut BTreeMap<String, NodeId>> {
+        match &mut self.nodes[id].kind {
+            Kind::Directory(children) => Some(children),
+            Kind::File => None,
+        }
+    }
+
+    fn is_directory(&self, id: NodeId) -> bool {
+        self.children(id).is_some()
     }
 
-    pub fn add_directory(&mut self, name: String) {
-        self.directories.entry(name).or_insert(Directory::new());
+    pub fn path(&self, id: NodeId) -> &str {
+        &self.nodes[id].path
     }
 
     pub fn total_size(&self) -> usize {
-        let mut result = 0;
-        for size in self.files.values() {
-            result += size;
+        self.nodes[ROOT].size
+    }
+
+    /// resolve an absolute path or a path relative to the current directory
+    pub fn resolve(&self, path: &str) -> Option<NodeId> {
+        let mut current = if path.starts_with('/') {
+            ROOT
+        } else {
+            self.cwd
+        };
+        for component in path.split('/').filter(|c| !c.is_empty()) {
+            current = match component {
+                "." => current,
+                ".." => self.nodes[current].parent.unwrap_or(ROOT),
+                name => *self.children(current)?.get(name)?,
+            };
         }
+        Some(current)
+    }
 
-        for directory in self.directories.values() {
-            result += directory.total_size();
+    fn add_size(&mut self, start: Option<NodeId>, delta: isize) {
+        let mut current = start;
+        while let Some(id) = current {
+            let node = &mut self.nodes[id];
+            node.size = node.size.checked_add_signed(delta).unwrap();
+            current = node.parent;
         }
+    }
 
-        result
+    fn child_path(&self, parent: NodeId, name: &str) -> String {
+        if parent == ROOT {
+            format!("/{name}")
+        } else {
+            format!("{}/{}", self.nodes[parent].path, name)
+        }
     }
 
-    pub fn descend(&mut self, dir_name: &str) -> &mut Directory {
-        self.directories.get_mut(dir_name).unwrap()
+    /// Add a file or directory to the current directory. Listing the same entry twice
+    /// doesn't count its size twice.
+    fn add(&mut self, name: &str, file_size: Option<usize>) -> Result<NodeId, String> {
+        let parent = self.cwd;
+        if let Some(&existing) = self.children(parent).unwrap().get(name) {
+            return match (file_size, self.is_directory(existing)) {
+                (None, true) => Ok(existing),
+                (Some(size), false) => {
+                    let delta = size as isize - self.nodes[existing].size as isize;
+                    self.add_size(Some(existing), delta);
+                    Ok(existing)
+                }
+                _ => Err(format!("{name} changed between file and directory")),
+            };
+        }
+
+        let id = self.nodes.len();
+        let kind = match file_size {
+            Some(_) => Kind::File,
+            None => Kind::Directory(BTreeMap::new()),
+        };
+        self.nodes.push(Node {
+            name: name.to_string(),
+            path: self.child_path(parent, name),
+            parent: Some(parent),
+            kind,
+            size: 0,
+        });
+        self.children_mut(parent)
+            .unwrap()
+            .insert(name.to_string(), id);
+        self.add_size(Some(id), file_size.unwrap_or(0) as isize);
+        Ok(id)
     }
 
-    pub fn subdirs(&self) -> std::collections::hash_map::Values<String, Directory> {
-        self.directories.values()
+    pub fn cd(&mut self, path: &str) -> Result<(), String> {
+        match self.resolve(path) {
+            Some(id) if self.is_directory(id) => {
+                self.cwd = id;
+                Ok(())
+            }
+            Some(_) => Err(format!("not a directory: {path}")),
+            None => Err(format!("no such directory: {path}")),
+        }
     }
-}
 
-pub fn walk_through_commands_from_file(path: &str) -> Directory {
-    let mut result = Directory::new();
-    let fd = File::open(path).unwrap();
-    let reader = BufReader::new(fd);
+    /// unlink a node from its parent, keeping its subtree intact
+    fn detach(&mut self, id: NodeId) {
+        let parent = self.nodes[id].parent.take().unwrap();
+        let name = self.nodes[id].name.clone();
+        self.children_mut(parent).unwrap().remove(&name);
+        self.add_size(Some(parent), -(self.nodes[id].size as isize));
+    }
 
-    let mut current_path: Vec<String> = vec![];
+    fn attach(&mut self, id: NodeId, parent: NodeId, name: &str) {
+        self.nodes[id].name = name.to_string();
+        self.nodes[id].parent = Some(parent);
+        self.children_mut(parent)
+            .unwrap()
+            .insert(name.to_string(), id);
+        self.add_size(Some(parent), self.nodes[id].size as isize);
+        self.update_paths(id);
+    }
 
-    for line in reader.lines() {
-        let line = line.unwrap();
-        let tokens: Vec<String> = line.split(' ').map(|s| s.to_string()).collect();
-        match tokens[0].as_str() {
-            "$" => {
-                match tokens[1].as_str() {
-                    "cd" => match tokens[2].as_str() {
-                        "/" => current_path.clear(),
-                        ".." => {
-                            current_path.pop();
-                        }
-                        name => current_path.push(name.to_string()),
-                    },
-                    "ls" => {
-                        // just parse the following lines
-                    }
-                    _ => {
-                        panic!("unsupported command: {}", tokens[1])
-                    }
-                }
+    fn update_paths(&mut self, id: NodeId) {
+        let parent = self.nodes[id].parent.unwrap();
+        self.nodes[id].path = self.child_path(parent, &self.nodes[id].name);
+        let children: Vec<NodeId> = self
+            .children(id)
+            .map(|children| children.values().cloned().collect())
+            .unwrap_or_default();
+        for child in children {
+            self.update_paths(child);
+        }
+    }
+
+    /// remove a file or a directory with everything below it
+    pub fn rm(&mut self, path: &str) -> Result<(), String> {
+        let id = self
+            .resolve(path)
+            .ok_or_else(|| format!("no such file or directory: {path}"))?;
+        if id == ROOT || self.is_ancestor(id, self.cwd) {
+            return Err(format!("refusing to remove {path}"));
+        }
+        self.detach(id);
+        Ok(())
+    }
+
+    /// Move into `to` if that is a directory, otherwise move and rename to `to`
+    pub fn mv(&mut self, from: &str, to: &str) -> Result<(), String> {
+        let id = self
+            .resolve(from)
+            .ok_or_else(|| format!("no such file or directory: {from}"))?;
+        let (parent, name) = match self.resolve(to) {
+            Some(target) if self.is_directory(target) => (target, self.nodes[id].name.clone()),
+            Some(_) => return Err(format!("{to} already exists")),
+            None => {
+                let (parent_path, name) = to.rsplit_once('/').unwrap_or(("", to));
+                let parent_path = if parent_path.is_empty() && to.starts_with('/') {
+                    "/"
+                } else {
+                    parent_path
+                };
+                let parent = self
+                    .resolve(parent_path)
+                    .filter(|parent| self.is_directory(*parent))
+                    .ok_or_else(|| format!("no such directory: {parent_path}"))?;
+                (parent, name.to_string())
             }
-            "dir" => {
-                let mut dir_pointer = &mut result;
-                for dir_name in &current_path {
-                    dir_pointer = dir_pointer.descend(&dir_name);
+        };
+        if id == ROOT || self.is_ancestor(id, parent) {
+            return Err(format!("cannot move {from} into itself"));
+        }
+        if self.children(parent).unwrap().contains_key(&name) {
+            return Err(format!("{to} already exists"));
+        }
+        self.detach(id);
+        self.attach(id, parent, &name);
+        Ok(())
+    }
+
+    fn is_ancestor(&self, ancestor: NodeId, mut id: NodeId) -> bool {
+        loop {
+            if id == ancestor {
+                return true;
+            }
+            match self.nodes[id].parent {
+                Some(parent) => id = parent,
+                None => return false,
+            }
+        }
+    }
+
+    /// all directories, children before their parents (like `du`)
+    fn directories(&self) -> Vec<NodeId> {
+        fn visit(fs: &FileSystem, id: NodeId, out: &mut Vec<NodeId>) {
+            for child in fs.children(id).unwrap().values() {
+                if fs.is_directory(*child) {
+                    visit(fs, *child, out);
                 }
-                dir_pointer.add_directory(tokens[1].to_string());
             }
-            size => {
-                let mut dir_pointer = &mut result;
-                for dir_name in &current_path {
-                    dir_pointer = dir_pointer.descend(&dir_name);
+            out.push(id);
+        }
+        let mut result = vec![];
+        visit(self, ROOT, &mut result);
+        result
+    }
+
+    pub fn directory_sizes(&self) -> Vec<usize> {
+        self.directories()
+            .iter()
+            .map(|id| self.nodes[*id].size)
+            .collect()
+    }
+
+    /// one line per directory with its total size and path
+    pub fn du(&self) -> String {
+        let mut result = String::new();
+        for id in self.directories() {
+            writeln!(result, "{}\t{}", self.nodes[id].size, self.path(id)).unwrap();
+        }
+        result
+    }
+
+    /// render the whole tree like the `tree` command, with sizes
+    pub fn tree(&self) -> String {
+        fn visit(fs: &FileSystem, id: NodeId, prefix: &str, out: &mut String) {
+            let children = fs.children(id).unwrap();
+            for (i, (name, child)) in children.iter().enumerate() {
+                let last = i + 1 == children.len();
+                let branch = if last { "└── " } else { "├── " };
+                let size = fs.nodes[*child].size;
+                if fs.is_directory(*child) {
+                    writeln!(out, "{prefix}{branch}{name}/ ({size})").unwrap();
+                    let indent = if last { "    " } else { "│   " };
+                    visit(fs, *child, &format!("{prefix}{indent}"), out);
+                } else {
+                    writeln!(out, "{prefix}{branch}{name} ({size})").unwrap();
                 }
-                dir_pointer.add_file(tokens[1].to_string(), size.parse().unwrap());
             }
         }
+        let mut result = format!("/ ({})\n", self.total_size());
+        visit(self, ROOT, "", &mut result);
+        result
+    }
+
+    /// apply a single line of a terminal transcript
+    pub fn apply(&mut self, line: &str) -> Result<(), String> {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["$", "cd", path] => self.cd(path),
+            ["$", "ls"] => Ok(()), // just parse the following lines
+            ["$", "rm", path] => self.rm(path),
+            ["$", "mv", from, to] => self.mv(from, to),
+            ["$", ..] => Err(format!("unsupported command: {line}")),
+            ["dir", name] => self.add(name, None).map(|_| ()),
+            [size, name] => {
+                let size = size.parse().map_err(|_| format!("bad file size: {size}"))?;
+                self.add(name, Some(size)).map(|_| ())
+            }
+            [] => Ok(()),
+            _ => Err(format!("cannot parse line: {line}")),
+        }
     }
-    result
 }
 
-pub fn traverse_directories_and_gather_sizes(dir: &Directory) -> Vec<usize> {
-    let mut result = vec![dir.total_size()];
-    for subdir in dir.subdirs() {
-        result.extend(traverse_directories_and_gather_sizes(&subdir));
+pub fn walk_through_commands_from_file(path: &str) -> Result<FileSystem, TranscriptError> {
+    let mut result = FileSystem::new();
+    let fd = File::open(path).unwrap();
+    let reader = BufReader::new(fd);
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.unwrap();
+        result.apply(&line).map_err(|message| TranscriptError {
+            line: i + 1,
+            message,
+        })?;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSCRIPT: &str = "$ cd /
+$ ls
+dir a
+14848514 b.txt
+8504156 c.dat
+dir d
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd e
+$ ls
+584 i
+$ cd ..
+$ cd ..
+$ cd d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k";
+
+    fn sample() -> FileSystem {
+        let mut fs = FileSystem::new();
+        for line in TRANSCRIPT.lines() {
+            fs.apply(line).unwrap();
+        }
+        fs
+    }
+
+    #[test]
+    fn sizes_of_the_sample() {
+        let fs = sample();
+        assert_eq!(fs.directory_sizes(), [584, 94853, 24933642, 48381165]);
+        assert_eq!(fs.total_size(), 48381165);
+    }
+
+    #[test]
+    fn du_of_the_sample() {
+        assert_eq!(
+            sample().du(),
+            "584\t/a/e\n94853\t/a\n24933642\t/d\n48381165\t/\n"
+        );
+    }
+
+    #[test]
+    fn tree_of_the_sample() {
+        let expected = "\
+/ (48381165)
+├── a/ (94853)
+│   ├── e/ (584)
+│   │   └── i (584)
+│   ├── f (29116)
+│   ├── g (2557)
+│   └── h.lst (62596)
+├── b.txt (14848514)
+├── c.dat (8504156)
+└── d/ (24933642)
+    ├── d.ext (5626152)
+    ├── d.log (8033020)
+    ├── j (4060174)
+    └── k (7214296)
+";
+        assert_eq!(sample().tree(), expected);
+    }
+
+    #[test]
+    fn listing_twice_does_not_count_twice() {
+        let mut fs = sample();
+        for line in ["$ cd /a/e", "$ ls", "584 i", "$ cd /", "$ ls", "dir a"] {
+            fs.apply(line).unwrap();
+        }
+        assert_eq!(fs.total_size(), 48381165);
+        assert!(fs.apply("dir b.txt").is_err());
+    }
+
+    #[test]
+    fn rm_and_mv_update_sizes_and_paths() {
+        let mut fs = sample();
+        fs.apply("$ mv /a/e /d/moved").unwrap();
+        let moved = fs.resolve("/d/moved/i").unwrap();
+        assert_eq!(fs.path(moved), "/d/moved/i");
+        assert_eq!(fs.directory_sizes(), [94269, 584, 24934226, 48381165]);
+        assert!(fs.apply("$ rm /d").is_err(), "the current directory");
+        fs.apply("$ cd /").unwrap();
+        fs.apply("$ rm d").unwrap();
+        assert_eq!(fs.total_size(), 48381165 - 24934226);
+        assert!(fs.resolve("/d").is_none());
+        assert!(fs.apply("$ rm /").is_err());
+        assert!(fs.apply("$ mv /a /a/e").is_err());
     }
-    result
 }
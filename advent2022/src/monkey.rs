@@ -1,152 +1,324 @@
+use std::collections::HashMap;
 use std::default::Default;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::mem::take;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::ops::{Div, Rem};
 use std::str::FromStr;
 
-pub enum Operand {
-    Value(usize),
-    Old,
+use aoc::expr::{EvalError, Expr, Number};
+use aoc::paragraphs::{self, FromBlock};
+
+/// Numbers that can be used for worry levels, e.g. `u64` or `u128`.
+///
+/// All arithmetic in the monkey formulas is checked, so an overflow is reported as an error
+/// instead of silently producing wrong answers.
+pub trait Worry:
+    Number + Default + Eq + Hash + Debug + FromStr + Rem<Output = Self> + Div<Output = Self>
+{
 }
 
-impl Operand {
-    pub fn resolve(&self, old: usize) -> usize {
-        match self {
-            Self::Value(v) => v.clone(),
-            Self::Old => old.clone(),
-        }
-    }
+impl<T> Worry for T where
+    T: Number + Default + Eq + Hash + Debug + FromStr + Rem<Output = T> + Div<Output = T>
+{
 }
 
-impl FromStr for Operand {
-    type Err = ();
+#[derive(Default)]
+pub struct Monkey<W> {
+    pub items: Vec<W>,
+    /// how the worry level changes during an inspection, in terms of `old`
+    pub formula: Expr,
+    pub divisor: W,
+    pub to_true: usize,
+    pub to_false: usize,
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(if s == "old" {
-            Self::Old
-        } else {
-            Self::Value(s.parse().unwrap())
-        })
-    }
+fn constant<W: Worry>(value: i64) -> W {
+    W::from_i64(value).unwrap()
 }
 
-pub enum Operator {
-    Plus,
-    Mul,
+fn gcd<W: Worry>(a: W, b: W) -> W {
+    if b == constant(0) {
+        a
+    } else {
+        gcd(b, a % b)
+    }
 }
 
-pub struct Formula {
-    left: Operand,
-    op: Operator,
-    right: Operand,
+/// Monkeys don't influence each other: the path of an item only depends on its own worry level.
+/// So we follow one item at a time through all rounds, which lets us skip over the rounds once
+/// the item is stuck in a cycle.
+pub struct Troop<W> {
+    monkeys: Vec<Monkey<W>>,
+    /// all divisibility tests give the same results modulo the least common multiple
+    common_multiple: W,
 }
 
-impl Formula {
-    pub fn apply(&self, old: usize) -> usize {
-        let left = self.left.resolve(old);
-        let right = self.right.resolve(old);
-        match self.op {
-            Operator::Plus => left + right,
-            Operator::Mul => left * right,
+impl<W: Worry> Troop<W> {
+    pub fn new(monkeys: Vec<Monkey<W>>) -> Result<Self, EvalError> {
+        let mut common_multiple: W = constant(1);
+        for monkey in &monkeys {
+            let factor = monkey.divisor / gcd(common_multiple, monkey.divisor);
+            common_multiple = common_multiple
+                .checked_mul(factor)
+                .ok_or(EvalError::Overflow)?;
         }
+        Ok(Self {
+            monkeys,
+            common_multiple,
+        })
     }
-}
 
-impl Default for Formula {
-    fn default() -> Self {
-        let left = Operand::Old;
-        let right = Operand::Old;
-        let op = Operator::Plus;
-        Self { left, op, right }
+    /// Let an item at the start of a round pass through the hands of the monkeys until the end
+    /// of the round. Items that are thrown to a monkey that has already had its turn wait for
+    /// the next round.
+    fn round(
+        &self,
+        (mut current, mut worry): (usize, W),
+        relief: W,
+        inspections: &mut [usize],
+    ) -> Result<(usize, W), EvalError> {
+        loop {
+            let monkey = &self.monkeys[current];
+            inspections[current] += 1;
+            worry = monkey.formula.eval_with("old", worry)? / relief;
+            if relief == constant(1) {
+                // reducing the worry level is only sound if we don't divide it afterwards
+                worry = worry % self.common_multiple;
+            }
+            let target = if worry % monkey.divisor == constant(0) {
+                monkey.to_true
+            } else {
+                monkey.to_false
+            };
+            if target <= current {
+                return Ok((target, worry));
+            }
+            current = target;
+        }
     }
-}
 
-#[derive(Default)]
-pub struct Monkey {
-    pub items: Vec<usize>,
-    pub formula: Formula,
-    pub divisor: usize,
-    pub to_true: usize,
-    pub to_false: usize,
-    pub inspections: usize,
-    pub stress_tolerance: usize,
+    fn follow_item(
+        &self,
+        mut state: (usize, W),
+        rounds: usize,
+        relief: W,
+        inspections: &mut [usize],
+    ) -> Result<(), EvalError> {
+        let mut seen: HashMap<(usize, W), usize> = HashMap::new();
+        let mut round = 0;
+        while round < rounds {
+            if let Some(first_seen) = seen.insert(state, round) {
+                // replay the cycle once to count its inspections and then skip over the repetitions
+                let cycle = round - first_seen;
+                let repetitions = (rounds - round) / cycle;
+                let mut cycle_inspections = vec![0; self.monkeys.len()];
+                for _ in 0..cycle {
+                    state = self.round(state, relief, &mut cycle_inspections)?;
+                }
+                for (total, n) in inspections.iter_mut().zip(cycle_inspections) {
+                    *total += n * repetitions;
+                }
+                round += cycle * repetitions;
+                for _ in round..rounds {
+                    state = self.round(state, relief, inspections)?;
+                }
+                return Ok(());
+            }
+            state = self.round(state, relief, inspections)?;
+            round += 1;
+        }
+        Ok(())
+    }
+
+    /// number of inspections per monkey after the given number of rounds. After each
+    /// inspection the worry level is divided by `relief`.
+    pub fn inspections(&self, rounds: usize, relief: i64) -> Result<Vec<usize>, EvalError> {
+        let relief: W = W::from_i64(relief).ok_or(EvalError::Overflow)?;
+        let mut result = vec![0; self.monkeys.len()];
+        for (i, monkey) in self.monkeys.iter().enumerate() {
+            for item in &monkey.items {
+                self.follow_item((i, *item), rounds, relief, &mut result)?;
+            }
+        }
+        Ok(result)
+    }
+
+    /// product of the two highest inspection counts
+    pub fn monkey_business(&self, rounds: usize, relief: i64) -> Result<usize, EvalError> {
+        let mut inspections = self.inspections(rounds, relief)?;
+        inspections.sort_unstable();
+        Ok(inspections.iter().rev().take(2).product())
+    }
 }
 
-impl Monkey {
-    pub fn throw(&mut self, common_divisor: usize) -> ThrownItems {
-        let mut result = ThrownItems {
-            targets: (0, 0),
-            items: (vec![], vec![]),
-        };
-        result.targets.0 = self.to_true;
-        result.targets.1 = self.to_false;
-
-        for item in self.items.drain(..) {
-            self.inspections += 1;
-            let new = self.formula.apply(item % common_divisor) / self.stress_tolerance;
-            if new % self.divisor == 0 {
-                result.items.0.push(new);
+impl<W: Worry> FromBlock for Monkey<W> {
+    type Err = String;
+
+    fn from_block(lines: &[String]) -> Result<Self, String> {
+        let mut monkey = Monkey::default();
+        for line in lines {
+            let line = line.trim();
+            if line.starts_with("Monkey ") {
+                continue;
+            } else if let Some(numbers) = line.strip_prefix("Starting items:") {
+                for n in numbers.split(',') {
+                    let item = n.trim().parse::<W>();
+                    monkey
+                        .items
+                        .push(item.map_err(|_| format!("bad item: {}", n))?);
+                }
+            } else if let Some(formula) = line.strip_prefix("Operation: new =") {
+                monkey.formula = formula
+                    .parse()
+                    .map_err(|err| format!("bad formula {}: {}", formula, err))?;
+            } else if let Some(divisor) = line.strip_prefix("Test: divisible by") {
+                let divisor = divisor.trim();
+                monkey.divisor = divisor
+                    .parse()
+                    .map_err(|_| format!("bad divisor: {}", divisor))?;
+            } else if line.starts_with("If true") {
+                monkey.to_true = last_number(line)?;
+            } else if line.starts_with("If false") {
+                monkey.to_false = last_number(line)?;
             } else {
-                result.items.1.push(new);
+                return Err(format!("unrecognized line:\n{}", line));
             }
         }
-        result
+        Ok(monkey)
     }
+}
 
-    pub fn catch(&mut self, items: &[usize]) {
-        self.items.extend_from_slice(items);
-    }
+fn last_number(line: &str) -> Result<usize, String> {
+    let last = line.split_whitespace().last().unwrap_or_default();
+    last.parse()
+        .map_err(|_| format!("bad monkey number: {}", last))
 }
 
-#[derive(Debug)]
-pub struct ThrownItems {
-    pub targets: (usize, usize),
-    pub items: (Vec<usize>, Vec<usize>),
+pub fn read_monkies_from_file<W: Worry>(path: &str) -> Vec<Monkey<W>> {
+    paragraphs::from_file(path)
+        .map(|block| Monkey::from_block(&block).unwrap_or_else(|err| panic!("{}", err)))
+        .collect()
 }
 
-pub fn read_monkies_from_file(path: &str, reduce_stress: bool) -> Vec<Monkey> {
-    let mut result = vec![];
-    let mut monkey = Monkey::default();
-    monkey.stress_tolerance = if reduce_stress { 3 } else { 1 };
-    let fd = File::open(path).unwrap();
-    let reader = BufReader::new(fd);
-    for line in reader.lines() {
-        let line = line.unwrap();
-        let line = line.trim();
-        if line.trim() == "" {
-            result.push(take(&mut monkey));
-            monkey.stress_tolerance = if reduce_stress { 3 } else { 1 };
-        } else if line.starts_with("Monkey ") {
-            continue;
-        } else if line.starts_with("Starting") {
-            let parts: Vec<&str> = line.split(':').collect();
-            let numbers: Vec<&str> = parts[1].split(',').map(|s| s.trim()).collect();
-            monkey
-                .items
-                .extend(numbers.iter().map(|n| n.parse::<usize>().unwrap()));
-        } else if line.starts_with("Operation") {
-            let parts: Vec<&str> = line.split('=').collect();
-            let formula_parts: Vec<&str> = parts[1].split_whitespace().collect();
-            monkey.formula.left = formula_parts[0].parse().unwrap();
-            monkey.formula.right = formula_parts[2].parse().unwrap();
-            monkey.formula.op = if formula_parts[1] == "+" {
-                Operator::Plus
-            } else {
-                Operator::Mul
-            };
-        } else if line.starts_with("Test:") {
-            let parts: Vec<&str> = line.split("by").collect();
-            monkey.divisor = parts[1].trim().parse().unwrap();
-        } else if line.starts_with("If true") {
-            let mut parts: Vec<&str> = line.split_whitespace().collect();
-            monkey.to_true = parts.pop().unwrap().parse().unwrap();
-        } else if line.starts_with("If false") {
-            let mut parts: Vec<&str> = line.split_whitespace().collect();
-            monkey.to_false = parts.pop().unwrap().parse().unwrap();
-        } else {
-            panic!("unrecognized line:\n{}", line);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "Monkey 0:
+  Starting items: 79, 98
+  Operation: new = old * 19
+  Test: divisible by 23
+    If true: throw to monkey 2
+    If false: throw to monkey 3
+
+Monkey 1:
+  Starting items: 54, 65, 75, 74
+  Operation: new = old + 6
+  Test: divisible by 19
+    If true: throw to monkey 2
+    If false: throw to monkey 0
+
+Monkey 2:
+  Starting items: 79, 60, 97
+  Operation: new = old * old
+  Test: divisible by 13
+    If true: throw to monkey 1
+    If false: throw to monkey 3
+
+Monkey 3:
+  Starting items: 74
+  Operation: new = old + 3
+  Test: divisible by 17
+    If true: throw to monkey 0
+    If false: throw to monkey 1
+";
+
+    const SMALL: &str = "Monkey 0:
+  Starting items: 3, 7
+  Operation: new = old * old
+  Test: divisible by 5
+    If true: throw to monkey 2
+    If false: throw to monkey 1
+
+Monkey 1:
+  Starting items: 4
+  Operation: new = old + 6
+  Test: divisible by 7
+    If true: throw to monkey 0
+    If false: throw to monkey 2
+
+Monkey 2:
+  Starting items: 1, 9
+  Operation: new = old * 2
+  Test: divisible by 3
+    If true: throw to monkey 1
+    If false: throw to monkey 0
+";
+
+    fn parse<W: Worry>(input: &str) -> Vec<Monkey<W>> {
+        paragraphs::paragraphs(input.as_bytes())
+            .map(|block| Monkey::from_block(&block).unwrap())
+            .collect()
+    }
+
+    /// turn by turn like in the puzzle description, without skipping any rounds
+    fn simulate(monkeys: &[Monkey<u64>], rounds: usize, relief: u64) -> Vec<usize> {
+        let common_multiple: u64 = monkeys.iter().map(|monkey| monkey.divisor).product();
+        let mut items: Vec<Vec<u64>> = monkeys.iter().map(|monkey| monkey.items.clone()).collect();
+        let mut inspections = vec![0; monkeys.len()];
+        for _ in 0..rounds {
+            for (i, monkey) in monkeys.iter().enumerate() {
+                for worry in std::mem::take(&mut items[i]) {
+                    inspections[i] += 1;
+                    let mut worry = monkey.formula.eval_with("old", worry).unwrap() / relief;
+                    if relief == 1 {
+                        worry %= common_multiple;
+                    }
+                    let target = if worry.is_multiple_of(monkey.divisor) {
+                        monkey.to_true
+                    } else {
+                        monkey.to_false
+                    };
+                    items[target].push(worry);
+                }
+            }
         }
+        inspections
+    }
+
+    #[test]
+    fn sample() {
+        let troop = Troop::new(parse::<u64>(SAMPLE)).unwrap();
+        assert_eq!(troop.inspections(20, 3).unwrap(), [101, 95, 7, 105]);
+        assert_eq!(troop.monkey_business(20, 3).unwrap(), 10605);
+        assert_eq!(troop.monkey_business(10000, 1).unwrap(), 2713310158);
+    }
+
+    #[test]
+    fn skipping_cycles_matches_simulation() {
+        // the worry levels only stay bounded without relief
+        for (input, rounds, relief) in [(SMALL, 1_000_000, 1), (SAMPLE, 10_000, 1), (SAMPLE, 20, 3)]
+        {
+            let expected = simulate(&parse(input), rounds, relief);
+            let troop = Troop::new(parse::<u64>(input)).unwrap();
+            assert_eq!(troop.inspections(rounds, relief as i64).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn overflow() {
+        let troop = Troop::new(parse::<u32>(SAMPLE)).unwrap();
+        assert_eq!(troop.monkey_business(20, 3), Ok(10605));
+        // without relief, old * old exceeds u32 before the worry level is reduced
+        let mut monkeys = parse::<u32>(SAMPLE);
+        monkeys[2].items.push(70000);
+        let troop = Troop::new(monkeys).unwrap();
+        assert_eq!(troop.monkey_business(1, 1), Err(EvalError::Overflow));
+
+        // the common multiple of the divisors doesn't fit
+        let mut monkeys = parse::<u32>(SMALL);
+        monkeys[0].divisor = 65521;
+        monkeys[1].divisor = 65519;
+        assert!(matches!(Troop::new(monkeys), Err(EvalError::Overflow)));
     }
-    result.push(monkey);
-    result
 }
@@ -1,143 +1,271 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub enum Weapon {
-    Rock,
-    Paper,
-    Scissors,
-}
+/// index into the cyclic order of the weapons
+pub type Weapon = usize;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Outcome {
-    Win,
-    Lose,
-    Draw,
+    Lose = 0,
+    Draw = 1,
+    Win = 2,
 }
 
-impl Outcome {
-    fn answer(&self, weapon: &Weapon) -> Weapon {
-        match self {
-            Outcome::Win => {
-                match weapon {
-                    Weapon::Rock => Weapon::Paper,
-                    Weapon::Paper => Weapon::Scissors,
-                    Weapon::Scissors => Weapon::Rock,
-                }
-            },
-            Outcome::Lose => {
-                match weapon {
-                    Weapon::Rock => Weapon::Scissors,
-                    Weapon::Paper => Weapon::Rock,
-                    Weapon::Scissors => Weapon::Paper,
-                }
-            },
-            Outcome::Draw => {
-                weapon.clone()
-            }
+/// A tournament where every weapon beats half of the others. Weapon `a` beats weapon `b` if
+/// `a - b` is odd (modulo the number of weapons), which gives the usual rules for
+/// `Rock, Paper, Scissors` as well as for `Rock, Paper, Scissors, Spock, Lizard`.
+#[derive(Debug, Clone)]
+pub struct Rules {
+    names: Vec<String>,
+    weapon_scores: Vec<usize>,
+    /// indexed by `Outcome`
+    outcome_scores: [usize; 3],
+}
+
+impl Rules {
+    /// Every weapon is worth its position (starting at 1), a loss 0, a draw 3 and a win 6 points
+    pub fn new(names: &[&str]) -> Self {
+        assert!(
+            names.len() % 2 == 1,
+            "only an odd number of weapons can be fair"
+        );
+        Self {
+            names: names.iter().map(|name| name.to_string()).collect(),
+            weapon_scores: (1..=names.len()).collect(),
+            outcome_scores: [0, 3, 6],
         }
     }
-}
 
-impl From<char> for Outcome {
-    fn from(c: char) -> Self {
-        match c {
-            'X' => Self::Lose,
-            'Y' => Self::Draw,
-            'Z' => Self::Win,
-            _ => panic!("Bad data"),
+    pub fn rock_paper_scissors() -> Self {
+        Self::new(&["Rock", "Paper", "Scissors"])
+    }
+
+    pub fn rock_paper_scissors_spock_lizard() -> Self {
+        Self::new(&["Rock", "Paper", "Scissors", "Spock", "Lizard"])
+    }
+
+    pub fn with_weapon_scores(mut self, scores: &[usize]) -> Self {
+        assert_eq!(scores.len(), self.names.len());
+        self.weapon_scores = scores.to_vec();
+        self
+    }
+
+    pub fn with_outcome_scores(mut self, lose: usize, draw: usize, win: usize) -> Self {
+        self.outcome_scores = [lose, draw, win];
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn name(&self, weapon: Weapon) -> &str {
+        &self.names[weapon]
+    }
+
+    pub fn outcome(&self, me: Weapon, you: Weapon) -> Outcome {
+        let difference = (me + self.len() - you) % self.len();
+        if difference == 0 {
+            Outcome::Draw
+        } else if difference % 2 == 1 {
+            Outcome::Win
+        } else {
+            Outcome::Lose
         }
     }
-}
 
-impl Weapon {
-    fn value(&self) -> usize {
-        match self {
-            Self::Rock => 1,
-            Self::Paper => 2,
-            Self::Scissors => 3,
+    pub fn score(&self, me: Weapon, you: Weapon) -> usize {
+        self.outcome_scores[self.outcome(me, you) as usize] + self.weapon_scores[me]
+    }
+
+    /// the most valuable weapon that leads to the desired outcome
+    pub fn answer(&self, you: Weapon, outcome: Outcome) -> Weapon {
+        (0..self.len())
+            .filter(|me| self.outcome(*me, you) == outcome)
+            .max_by_key(|me| self.weapon_scores[*me])
+            .unwrap()
+    }
+
+    /// the weapon with the highest score against `you`
+    pub fn best_response(&self, you: Weapon) -> Weapon {
+        (0..self.len())
+            .max_by_key(|me| self.score(*me, you))
+            .unwrap()
+    }
+
+    /// Play the best response in every round
+    pub fn evaluate<I: IntoIterator<Item = Weapon>>(&self, opponent: I) -> Evaluation {
+        let mut result = Evaluation {
+            total_score: 0,
+            responses: vec![0; self.len()],
+        };
+        for you in opponent {
+            let me = self.best_response(you);
+            result.total_score += self.score(me, you);
+            result.responses[me] += 1;
         }
+        result
     }
 }
 
-impl From<char> for Weapon {
-    fn from(c: char) -> Self {
-        match c {
-            'A' | 'X' => Self::Rock,
-            'B' | 'Y' => Self::Paper,
-            'C' | 'Z' => Self::Scissors,
-            _ => panic!("Something else")
-        }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Evaluation {
+    pub total_score: usize,
+    /// how often each weapon has been played
+    pub responses: Vec<usize>,
+}
+
+impl Evaluation {
+    /// share of the rounds in which each weapon has been played
+    pub fn distribution(&self) -> Vec<f64> {
+        let rounds: usize = self.responses.iter().sum();
+        self.responses
+            .iter()
+            .map(|count| *count as f64 / rounds.max(1) as f64)
+            .collect()
     }
 }
 
-pub struct Choice {
-    you: Weapon,
-    me: Weapon,
+/// Maps the letters of the strategy guide to weapons and outcomes
+#[derive(Debug, Clone)]
+pub struct Symbols {
+    opponent: HashMap<char, Weapon>,
+    me: HashMap<char, Weapon>,
+    outcomes: HashMap<char, Outcome>,
 }
 
-impl Choice {
-    pub fn new(you: Weapon, me: Weapon) -> Self {
-        Self{you, me}
+impl Symbols {
+    /// The n-th letter of `opponent` and `me` stands for the n-th weapon. `outcomes` lists the
+    /// letters for losing, drawing and winning.
+    pub fn new(opponent: &str, me: &str, outcomes: &str) -> Self {
+        let outcomes: Vec<char> = outcomes.chars().collect();
+        assert_eq!(outcomes.len(), 3);
+        Self {
+            opponent: opponent.chars().zip(0..).collect(),
+            me: me.chars().zip(0..).collect(),
+            outcomes: outcomes
+                .into_iter()
+                .zip([Outcome::Lose, Outcome::Draw, Outcome::Win])
+                .collect(),
+        }
     }
 
-    pub fn score(&self) -> usize {
-        let base = if self.you == self.me {
-            3
-        } else {
-            match self.you {
-                Weapon::Rock => {
-                    if self.me == Weapon::Paper {
-                        6
-                    } else {
-                        0
-                    }
-                },
-                Weapon::Paper => {
-                    if self.me == Weapon::Scissors {
-                        6
-                    } else {
-                        0
-                    }
-                },
-                Weapon::Scissors => {
-                    if self.me == Weapon::Rock {
-                        6
-                    } else {
-                        0
-                    }
-                },
-            }
-        };
-        base + self.me.value()
+    pub fn opponent(&self, c: char) -> Weapon {
+        *self
+            .opponent
+            .get(&c)
+            .unwrap_or_else(|| panic!("Bad data: {c}"))
+    }
+
+    pub fn me(&self, c: char) -> Weapon {
+        *self.me.get(&c).unwrap_or_else(|| panic!("Bad data: {c}"))
+    }
+
+    pub fn outcome(&self, c: char) -> Outcome {
+        *self
+            .outcomes
+            .get(&c)
+            .unwrap_or_else(|| panic!("Bad data: {c}"))
     }
 }
 
-pub fn read_choices_from_file(path: &str) -> Vec<Choice> {
-   let mut result = vec![];
-   let fd = File::open(path).unwrap();
-   let reader = BufReader::new(fd);
-   for line in reader.lines() {
-        let line = line.unwrap();
-        let mut iter = line.chars();
-        let you: Weapon = iter.next().unwrap().into();
-        iter.next();
-        let me: Weapon = iter.next().unwrap().into();
-        result.push(Choice::new(you, me));
-   }
-   result
+impl Default for Symbols {
+    fn default() -> Self {
+        Self::new("ABC", "XYZ", "XYZ")
+    }
 }
 
-pub fn read_outcome_based_choices_from_file(path: &str) -> Vec<Choice> {
-   let mut result = vec![];
-   let fd = File::open(path).unwrap();
-   let reader = BufReader::new(fd);
-   for line in reader.lines() {
+/// the two letters of every round
+pub fn read_guide_from_file(path: &str) -> Vec<(char, char)> {
+    let mut result = vec![];
+    let fd = File::open(path).unwrap();
+    let reader = BufReader::new(fd);
+    for line in reader.lines() {
         let line = line.unwrap();
-        let mut iter = line.chars();
-        let you: Weapon = iter.next().unwrap().into();
-        iter.next();
-        let outcome: Outcome = iter.next().unwrap().into();
-        result.push(Choice::new(you, outcome.answer(&you)));
-   }
-   result
+        let mut iter = line.split_whitespace();
+        if let (Some(you), Some(me)) = (iter.next(), iter.next()) {
+            result.push((you.chars().next().unwrap(), me.chars().next().unwrap()));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rock_paper_scissors_spock_lizard_table() {
+        let rules = Rules::rock_paper_scissors_spock_lizard();
+        let index = |name: &str| (0..rules.len()).find(|w| rules.name(*w) == name).unwrap();
+        let beats = [
+            ("Rock", ["Scissors", "Lizard"]),
+            ("Paper", ["Rock", "Spock"]),
+            ("Scissors", ["Paper", "Lizard"]),
+            ("Spock", ["Scissors", "Rock"]),
+            ("Lizard", ["Spock", "Paper"]),
+        ];
+        for (winner, losers) in beats {
+            for loser in losers {
+                assert_eq!(rules.outcome(index(winner), index(loser)), Outcome::Win);
+                assert_eq!(rules.outcome(index(loser), index(winner)), Outcome::Lose);
+            }
+        }
+        for weapon in 0..rules.len() {
+            assert_eq!(rules.outcome(weapon, weapon), Outcome::Draw);
+        }
+    }
+
+    #[test]
+    fn scores_of_the_sample_guide() {
+        let rules = Rules::rock_paper_scissors();
+        let symbols = Symbols::default();
+        let guide = [('A', 'Y'), ('B', 'X'), ('C', 'Z')];
+        let total: usize = guide
+            .iter()
+            .map(|(you, me)| rules.score(symbols.me(*me), symbols.opponent(*you)))
+            .sum();
+        assert_eq!(total, 15);
+        let total: usize = guide
+            .iter()
+            .map(|(you, outcome)| {
+                let you = symbols.opponent(*you);
+                rules.score(rules.answer(you, symbols.outcome(*outcome)), you)
+            })
+            .sum();
+        assert_eq!(total, 12);
+    }
+
+    #[test]
+    fn evaluate_plays_the_best_response() {
+        let rules = Rules::rock_paper_scissors();
+        let evaluation = rules.evaluate([0, 0, 1, 2]);
+        // paper twice, scissors and rock once, always winning
+        assert_eq!(evaluation.responses, [1, 2, 1]);
+        assert_eq!(evaluation.total_score, 4 * 6 + 2 + 2 + 3 + 1);
+        assert_eq!(evaluation.distribution(), [0.25, 0.5, 0.25]);
+        assert_eq!(rules.evaluate([]).distribution(), [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn custom_scores_change_the_best_response() {
+        let rules = Rules::rock_paper_scissors()
+            .with_weapon_scores(&[1, 2, 10])
+            .with_outcome_scores(0, 1, 2);
+        // scissors are worth losing with
+        assert_eq!(rules.best_response(0), 2);
+        assert_eq!(rules.answer(0, Outcome::Win), 1);
+        assert_eq!(rules.score(2, 0), 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "odd number")]
+    fn even_number_of_weapons_is_unfair() {
+        Rules::new(&["Rock", "Paper"]);
+    }
 }
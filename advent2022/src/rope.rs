@@ -1,8 +1,7 @@
-use std::collections::HashSet;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
-use crate::direction::Direction;
+use crate::matrix::Matrix;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Coordinate {
@@ -11,17 +10,6 @@ pub struct Coordinate {
 }
 
 impl Coordinate {
-    pub fn project(&self, direction: Direction) -> Self {
-        let mut result = self.clone();
-        match direction {
-            Direction::North => result.y += 1,
-            Direction::South => result.y -= 1,
-            Direction::East => result.x += 1,
-            Direction::West => result.x -= 1,
-        }
-        result
-    }
-
     pub fn too_far(&self, other: &Coordinate) -> bool {
         let x = self.x.abs_diff(other.x);
         let y = self.y.abs_diff(other.y);
@@ -31,60 +19,323 @@ impl Coordinate {
     pub fn follow(&mut self, predecessor: &Coordinate) {
         let pred = predecessor;
         if self.too_far(pred) {
-            self.x += if pred.x > self.x {
-                1
-            } else if pred.x < self.x {
-                -1
-            } else {
-                0
-            };
-            self.y += if pred.y > self.y {
-                1
-            } else if pred.y < self.y {
-                -1
+            self.x += (pred.x - self.x).signum();
+            self.y += (pred.y - self.y).signum();
+        }
+    }
+}
+
+/// Move the head `steps` times by one unit step. Diagonal steps are allowed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Move {
+    pub dx: isize,
+    pub dy: isize,
+    pub steps: usize,
+}
+
+/// the area that the head (and therefore every other knot) can reach
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Extent {
+    pub min: Coordinate,
+    pub max: Coordinate,
+}
+
+impl Extent {
+    pub fn of_moves(moves: &[Move]) -> Self {
+        let mut head = Coordinate { x: 0, y: 0 };
+        let mut result = Extent {
+            min: head,
+            max: head,
+        };
+        for m in moves {
+            head.x += m.dx * m.steps as isize;
+            head.y += m.dy * m.steps as isize;
+            result.min.x = result.min.x.min(head.x);
+            result.min.y = result.min.y.min(head.y);
+            result.max.x = result.max.x.max(head.x);
+            result.max.y = result.max.y.max(head.y);
+        }
+        result
+    }
+
+    pub fn width(&self) -> usize {
+        self.min.x.abs_diff(self.max.x) + 1
+    }
+
+    pub fn height(&self) -> usize {
+        self.min.y.abs_diff(self.max.y) + 1
+    }
+
+    /// column and row in a grid where the top row is at `max.y`
+    fn cell(&self, c: &Coordinate) -> (usize, usize) {
+        (c.x.abs_diff(self.min.x), c.y.abs_diff(self.max.y))
+    }
+
+    /// coordinates of every cell, top row first
+    fn rows(&self) -> impl Iterator<Item = Vec<Coordinate>> + '_ {
+        (self.min.y..=self.max.y).rev().map(|y| {
+            (self.min.x..=self.max.x)
+                .map(|x| Coordinate { x, y })
+                .collect()
+        })
+    }
+}
+
+/// A rope whose knots all start at the origin. Every knot remembers where it has been.
+pub struct Rope {
+    knots: Vec<Coordinate>,
+    extent: Extent,
+    /// one grid per knot
+    visited: Vec<Matrix<bool>>,
+    visited_counts: Vec<usize>,
+}
+
+impl Rope {
+    /// Apply all moves and call `on_step` after every unit step. The grids are sized for exactly
+    /// these moves, so the rope can't be moved any further afterwards.
+    pub fn simulate<F: FnMut(&Rope)>(length: usize, moves: &[Move], mut on_step: F) -> Self {
+        assert!(length > 0);
+        let extent = Extent::of_moves(moves);
+        let mut result = Self {
+            knots: vec![Coordinate { x: 0, y: 0 }; length],
+            extent,
+            visited: (0..length)
+                .map(|_| Matrix::new(extent.height(), extent.width(), false))
+                .collect(),
+            visited_counts: vec![0; length],
+        };
+        result.mark_visited();
+        for m in moves {
+            for _ in 0..m.steps {
+                result.step(m.dx, m.dy);
+                on_step(&result);
+            }
+        }
+        result
+    }
+
+    pub fn knots(&self) -> &[Coordinate] {
+        &self.knots
+    }
+
+    pub fn extent(&self) -> &Extent {
+        &self.extent
+    }
+
+    fn mark_visited(&mut self) {
+        for (i, knot) in self.knots.iter().enumerate() {
+            let (x, y) = self.extent.cell(knot);
+            if !self.visited[i].get(x, y, true) {
+                self.visited[i].set(true, x, y, true);
+                self.visited_counts[i] += 1;
+            }
+        }
+    }
+
+    /// move the head by a single unit step and let the other knots follow
+    fn step(&mut self, dx: isize, dy: isize) {
+        self.knots[0].x += dx;
+        self.knots[0].y += dy;
+        for i in 1..self.knots.len() {
+            let pred = self.knots[i - 1];
+            self.knots[i].follow(&pred);
+        }
+        self.mark_visited();
+    }
+
+    /// number of distinct places that the given knot has visited
+    pub fn visited_count(&self, knot: usize) -> usize {
+        self.visited_counts[knot]
+    }
+
+    pub fn has_visited(&self, knot: usize, c: &Coordinate) -> bool {
+        let (x, y) = self.extent.cell(c);
+        self.visited[knot].get(x, y, true)
+    }
+
+    /// Draw the knots like the puzzle does: `H` is the head, the others are numbered (or `T` for
+    /// a rope with two knots) and `s` marks the start
+    pub fn render(&self) -> String {
+        self.draw(|c| match self.knots.iter().position(|knot| knot == c) {
+            Some(0) => 'H',
+            Some(_) if self.knots.len() == 2 => 'T',
+            Some(i) => char::from_digit(i as u32 % 10, 10).unwrap(),
+            None if c.x == 0 && c.y == 0 => 's',
+            None => '.',
+        })
+    }
+
+    /// draw every place that the given knot has visited
+    pub fn render_visited(&self, knot: usize) -> String {
+        self.draw(|c| {
+            if c.x == 0 && c.y == 0 {
+                's'
+            } else if self.has_visited(knot, c) {
+                '#'
             } else {
-                0
-            };
+                '.'
+            }
+        })
+    }
+
+    fn draw<F: Fn(&Coordinate) -> char>(&self, symbol: F) -> String {
+        let mut result = String::with_capacity((self.extent.width() + 1) * self.extent.height());
+        for row in self.extent.rows() {
+            result.extend(row.iter().map(&symbol));
+            result.push('\n');
         }
+        result
     }
 }
 
-pub(crate) fn read_moves_from_file(path: &str) -> Vec<Direction> {
+/// Read lines like `R 4`. Diagonal moves combine two directions, e.g. `UL 3`.
+pub fn read_moves_from_file(path: &str) -> Vec<Move> {
     let mut result = vec![];
     let fd = File::open(path).unwrap();
     let reader = BufReader::new(fd);
     for line in reader.lines() {
         let line = line.unwrap();
-        let parts: Vec<&str> = line.split(' ').collect();
-        let direction = match parts[0] {
-            "D" => Direction::South,
-            "U" => Direction::North,
-            "L" => Direction::West,
-            "R" => Direction::East,
-            _ => panic!("Unknown direction: {}", parts[0]),
+        let Some((direction, steps)) = line.trim().split_once(' ') else {
+            continue;
         };
-        let times = parts[1].parse::<usize>().unwrap();
-        for _ in 0..times {
-            result.push(direction);
+        let (mut dx, mut dy) = (0isize, 0isize);
+        for c in direction.chars() {
+            match c {
+                'D' => dy -= 1,
+                'U' => dy += 1,
+                'L' => dx -= 1,
+                'R' => dx += 1,
+                _ => panic!("Unknown direction: {}", direction),
+            }
         }
+        assert!(
+            dx.abs() <= 1 && dy.abs() <= 1,
+            "Not a unit step: {}",
+            direction
+        );
+        let steps = steps.parse::<usize>().unwrap();
+        result.push(Move { dx, dy, steps });
     }
     result
 }
 
-pub(crate) fn visited_places(rope_length: usize, moves: &Vec<Direction>) -> HashSet<Coordinate> {
-    let mut knots = vec![Coordinate { x: 0, y: 0 }; rope_length];
-    let mut result = HashSet::new();
-    result.insert(knots.last().cloned().unwrap());
-    for dir in moves {
-        for i in 0..rope_length {
-            if i == 0 {
-                knots[0] = knots[0].project(*dir);
-            } else {
-                let pred = knots[i - 1];
-                knots[i].follow(&pred);
-            }
-        }
-        result.insert(knots.last().cloned().unwrap());
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moves(spec: &str) -> Vec<Move> {
+        spec.split(',')
+            .map(|m| {
+                let (direction, steps) = m.trim().split_once(' ').unwrap();
+                let (dx, dy) = match direction {
+                    "R" => (1, 0),
+                    "L" => (-1, 0),
+                    "U" => (0, 1),
+                    "D" => (0, -1),
+                    "UL" => (-1, 1),
+                    _ => unreachable!(),
+                };
+                Move {
+                    dx,
+                    dy,
+                    steps: steps.parse().unwrap(),
+                }
+            })
+            .collect()
+    }
+
+    const SMALL: &str = "R 4, U 4, L 3, D 1, R 4, D 1, L 5, R 2";
+    const LARGE: &str = "R 5, U 8, L 8, D 3, R 17, D 10, L 25, U 20";
+
+    #[test]
+    fn extent_covers_the_head() {
+        let extent = Extent::of_moves(&moves(SMALL));
+        assert_eq!((extent.width(), extent.height()), (6, 5));
+        let extent = Extent::of_moves(&moves(LARGE));
+        assert_eq!(extent.min, Coordinate { x: -11, y: -5 });
+        assert_eq!(extent.max, Coordinate { x: 14, y: 15 });
+    }
+
+    #[test]
+    fn render_the_final_state() {
+        let rope = Rope::simulate(2, &moves(SMALL), |_| ());
+        assert_eq!(rope.render(), "......\n......\n.TH...\n......\ns.....\n");
+        assert_eq!(
+            rope.knots(),
+            [Coordinate { x: 2, y: 2 }, Coordinate { x: 1, y: 2 }]
+        );
+    }
+
+    #[test]
+    fn render_every_step() {
+        let mut frames = vec![];
+        Rope::simulate(2, &moves("R 4, U 1"), |rope| frames.push(rope.render()));
+        assert_eq!(
+            frames,
+            [
+                ".....\nTH...\n",
+                ".....\nsTH..\n",
+                ".....\ns.TH.\n",
+                ".....\ns..TH\n",
+                "....H\ns..T.\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn render_visited_of_the_small_sample() {
+        let rope = Rope::simulate(2, &moves(SMALL), |_| ());
+        assert_eq!(rope.visited_count(1), 13);
+        assert_eq!(
+            rope.render_visited(1),
+            "..##..\n...##.\n.####.\n....#.\ns###..\n"
+        );
+    }
+
+    #[test]
+    fn render_visited_of_the_large_sample() {
+        let rope = Rope::simulate(10, &moves(LARGE), |_| ());
+        let expected = "\
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+#.........................
+#.............###.........
+#............#...#........
+.#..........#.....#.......
+..#..........#.....#......
+...#........#.......#.....
+....#......s.........#....
+.....#..............#.....
+......#............#......
+.......#..........#.......
+........#........#........
+.........########.........
+";
+        assert_eq!(rope.visited_count(9), 36);
+        assert_eq!(rope.render_visited(9), expected);
+        assert!(rope.has_visited(9, &Coordinate { x: -11, y: 6 }));
+        assert!(!rope.has_visited(9, &Coordinate { x: 14, y: 15 }));
+        assert_eq!(rope.extent(), &Extent::of_moves(&moves(LARGE)));
+    }
+
+    #[test]
+    fn diagonal_moves() {
+        let rope = Rope::simulate(3, &moves("UL 3"), |_| ());
+        assert_eq!(
+            rope.knots(),
+            [
+                Coordinate { x: -3, y: 3 },
+                Coordinate { x: -2, y: 2 },
+                Coordinate { x: -1, y: 1 }
+            ]
+        );
+        assert_eq!(rope.visited_count(2), 2);
     }
-    result
 }
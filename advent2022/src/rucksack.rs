@@ -1,76 +1,182 @@
-use std::collections::HashSet;
 use std::io::{BufRead, BufReader};
 
+/// Both compartments are sets of item priorities, bit `n` stands for priority `n`
 pub struct Rucksack {
-    left: Vec<usize>,
-    right: Vec<usize>,
+    left: u64,
+    right: u64,
+}
+
+#[derive(Debug)]
+pub struct RucksackError {
+    pub line: usize,
+    pub message: String,
 }
 
 impl Rucksack {
-    pub fn new(line: String) -> Self {
-        let mut left = vec![];
-        let mut right = vec![];
-        let num_items: usize = line.len() / 2;
-        for (i, c) in line.chars().enumerate() {
-            let value = get_value(c);
-            if i < num_items {
-                left.push(value);
-            } else {
-                right.push(value);
-            }
+    pub fn new(line: &str) -> Result<Self, String> {
+        let items = line
+            .chars()
+            .map(|c| get_value(c).ok_or_else(|| format!("not an item: {c:?}")))
+            .collect::<Result<Vec<usize>, String>>()?;
+        if !items.len().is_multiple_of(2) {
+            return Err(format!("odd number of items: {}", items.len()));
         }
-        Self { left, right }
+        let (left, right) = items.split_at(items.len() / 2);
+        Ok(Self {
+            left: to_bitmask(left),
+            right: to_bitmask(right),
+        })
     }
 
-    pub(crate) fn get_duplicate_item(&self) -> usize {
-        let mut set_left = HashSet::new();
-        for item in self.left.iter() {
-            set_left.insert(item.clone());
-        }
-        let mut set_right = HashSet::new();
-        for item in self.right.iter() {
-            set_right.insert(item.clone());
-        }
-        for duplicate in set_left.intersection(&set_right) {
-            return *duplicate;
-        }
-        0
+    /// the priority of the only item type that is in both compartments
+    pub fn get_duplicate_item(&self) -> Option<usize> {
+        single_item(self.left & self.right)
     }
 
-    pub(crate) fn to_set(&self) -> HashSet<usize> {
-        let mut result = HashSet::from_iter(self.left.iter().map(|x| x.clone()));
-        result.extend(self.right.iter());
-        result
+    pub fn items(&self) -> u64 {
+        self.left | self.right
     }
 }
 
-fn get_value(c: char) -> usize {
-    let numeric = c as usize;
-    if 'A' <= c && c <= 'Z' {
-        numeric - 65 + 27
+fn to_bitmask(priorities: &[usize]) -> u64 {
+    priorities.iter().fold(0, |result, p| result | 1 << p)
+}
+
+fn single_item(items: u64) -> Option<usize> {
+    if items.count_ones() == 1 {
+        Some(items.trailing_zeros() as usize)
     } else {
-        numeric - 97 + 1
+        None
+    }
+}
+
+fn get_value(c: char) -> Option<usize> {
+    match c {
+        'a'..='z' => Some(c as usize - 'a' as usize + 1),
+        'A'..='Z' => Some(c as usize - 'A' as usize + 27),
+        _ => None,
     }
 }
 
-pub fn get_rucksacks_from_file(path: &str) -> Vec<Rucksack> {
+pub fn get_rucksacks_from_file(path: &str) -> Result<Vec<Rucksack>, RucksackError> {
     let mut result = vec![];
     let fd = std::fs::File::open(path).unwrap();
     let reader = BufReader::new(fd);
-    for line in reader.lines() {
-        let rucksack = Rucksack::new(line.unwrap());
+    for (i, line) in reader.lines().enumerate() {
+        let rucksack =
+            Rucksack::new(line.unwrap().trim_end()).map_err(|message| RucksackError {
+                line: i + 1,
+                message,
+            })?;
         result.push(rucksack);
     }
-    result
+    Ok(result)
+}
+
+/// the priority of the only item type that every rucksack of the group carries
+pub fn get_badge(group: &[Rucksack]) -> Option<usize> {
+    let common = group.iter().map(Rucksack::items).reduce(|a, b| a & b)?;
+    single_item(common)
+}
+
+/// The sum of the duplicate items, one rucksack per line. A rucksack without exactly one
+/// duplicate item type is an error.
+pub fn duplicate_item_sum(rucksacks: &[Rucksack]) -> Result<usize, RucksackError> {
+    let mut result = 0;
+    for (i, rucksack) in rucksacks.iter().enumerate() {
+        result += rucksack.get_duplicate_item().ok_or_else(|| RucksackError {
+            line: i + 1,
+            message: String::from("no single item type in both compartments"),
+        })?;
+    }
+    Ok(result)
 }
 
-pub fn get_badge(r1: &Rucksack, r2: &Rucksack, r3: &Rucksack) -> usize {
-    let s1 = r1.to_set();
-    let s2 = r2.to_set();
-    let s3 = r3.to_set();
-    let set4: HashSet<usize> = HashSet::from_iter(s1.intersection(&s2).into_iter().cloned());
-    for badge in set4.intersection(&s3) {
-        return *badge;
+/// The sum of the badges of every group of `group_size` consecutive rucksacks. Every group needs
+/// exactly one badge and there must not be any rucksacks left over.
+pub fn badge_sum(rucksacks: &[Rucksack], group_size: usize) -> Result<usize, RucksackError> {
+    assert!(group_size > 0);
+    let groups = rucksacks.chunks_exact(group_size);
+    if !groups.remainder().is_empty() {
+        return Err(RucksackError {
+            line: rucksacks.len() - groups.remainder().len() + 1,
+            message: format!("not part of a complete group of {group_size}"),
+        });
+    }
+    let mut result = 0;
+    for (i, group) in groups.enumerate() {
+        result += get_badge(group).ok_or_else(|| RucksackError {
+            line: i * group_size + 1,
+            message: String::from("no single badge in this group"),
+        })?;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: [&str; 6] = [
+        "vJrwpWtwJgWrhcsFMMfFFhFp",
+        "jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL",
+        "PmmdzqPrVvPwwTWBwg",
+        "wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn",
+        "ttgJtRGJQctTZtZT",
+        "CrZsJsPPZsGzwwsLwLmpwMDw",
+    ];
+
+    fn sample() -> Vec<Rucksack> {
+        SAMPLE
+            .iter()
+            .map(|line| Rucksack::new(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn sums_of_the_sample() {
+        let rucksacks = sample();
+        assert_eq!(rucksacks[0].get_duplicate_item(), Some(16));
+        assert_eq!(duplicate_item_sum(&rucksacks).unwrap(), 157);
+        assert_eq!(badge_sum(&rucksacks, 3).unwrap(), 70);
+    }
+
+    #[test]
+    fn rejects_non_letters() {
+        assert_eq!(Rucksack::new("é").err().unwrap(), "not an item: 'é'");
+        assert!(Rucksack::new("ab1c").is_err());
+        assert!(Rucksack::new("abc").is_err());
+    }
+
+    #[test]
+    fn reports_rucksacks_without_a_single_duplicate() {
+        let rucksacks = [Rucksack::new("aa").unwrap(), Rucksack::new("abcd").unwrap()];
+        assert_eq!(duplicate_item_sum(&rucksacks).unwrap_err().line, 2);
+        let rucksacks = [Rucksack::new("abab").unwrap()];
+        assert_eq!(duplicate_item_sum(&rucksacks).unwrap_err().line, 1);
+    }
+
+    #[test]
+    fn reports_bad_groups() {
+        let mut rucksacks = sample();
+        rucksacks.push(Rucksack::new("aa").unwrap());
+        assert_eq!(badge_sum(&rucksacks, 3).unwrap_err().line, 7);
+        rucksacks.truncate(3);
+        rucksacks[2] = Rucksack::new("xx").unwrap();
+        assert_eq!(badge_sum(&rucksacks, 3).unwrap_err().line, 1);
+    }
+
+    #[test]
+    fn other_group_sizes() {
+        let pairs: Vec<Rucksack> = ["abAB", "bcCD", "xyXY", "xzZW"]
+            .iter()
+            .map(|line| Rucksack::new(line).unwrap())
+            .collect();
+        assert_eq!(badge_sum(&pairs, 2).unwrap(), 2 + 24);
+        assert_eq!(badge_sum(&pairs, 1).unwrap_err().line, 1);
+        let rucksacks = sample();
+        let err = badge_sum(&rucksacks, 4).unwrap_err();
+        assert_eq!(err.line, 5);
+        assert_eq!(err.message, "not part of a complete group of 4");
     }
-    0
 }
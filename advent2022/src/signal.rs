@@ -1,17 +1,115 @@
-use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::fs::File;
-use std::io::Read;
+use std::io::{BufReader, Read};
 
-pub(crate) fn find_signal_start_in_file(path: &str, marker_size: usize) -> usize {
-    let mut fd = File::open(path).unwrap();
+/// Yields the position right after every window of `size` distinct letters, in O(1) per byte.
+/// Anything but a lowercase letter can't be part of a marker and starts a new window.
+pub struct Markers<I> {
+    bytes: I,
+    size: usize,
+    window: VecDeque<u8>,
+    counts: [usize; 26],
+    /// how many letters occur more than once in the window
+    duplicates: usize,
+    position: usize,
+}
+
+impl<I: Iterator<Item = u8>> Iterator for Markers<I> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        for byte in self.bytes.by_ref() {
+            self.position += 1;
+            if !byte.is_ascii_lowercase() {
+                self.window.clear();
+                self.counts = [0; 26];
+                self.duplicates = 0;
+                continue;
+            }
+
+            let letter = (byte - b'a') as usize;
+            self.counts[letter] += 1;
+            if self.counts[letter] == 2 {
+                self.duplicates += 1;
+            }
+            self.window.push_back(byte);
+
+            if self.window.len() > self.size {
+                let letter = (self.window.pop_front().unwrap() - b'a') as usize;
+                self.counts[letter] -= 1;
+                if self.counts[letter] == 1 {
+                    self.duplicates -= 1;
+                }
+            }
+            if self.window.len() == self.size && self.duplicates == 0 {
+                return Some(self.position);
+            }
+        }
+        None
+    }
+}
+
+pub fn markers<I: IntoIterator<Item = u8>>(bytes: I, size: usize) -> Markers<I::IntoIter> {
+    Markers {
+        bytes: bytes.into_iter(),
+        size,
+        window: VecDeque::with_capacity(size + 1),
+        counts: [0; 26],
+        duplicates: 0,
+        position: 0,
+    }
+}
 
-    let mut contents = String::new();
-    fd.read_to_string(&mut contents).unwrap();
-    for (i, four) in contents.as_bytes().windows(marker_size).enumerate() {
-        let set: HashSet<&u8> = HashSet::from_iter(four);
-        if set.len() == marker_size {
-            return marker_size + i;
+/// number of bytes that have to be read until the first marker is complete
+pub fn find_marker<I: IntoIterator<Item = u8>>(bytes: I, size: usize) -> Option<usize> {
+    markers(bytes, size).next()
+}
+
+/// read the datastream until the first whitespace
+pub fn find_marker_in<R: Read>(reader: R, size: usize) -> Option<usize> {
+    let bytes = BufReader::new(reader)
+        .bytes()
+        .map(Result::unwrap)
+        .take_while(|b| !b.is_ascii_whitespace());
+    find_marker(bytes, size)
+}
+
+pub fn find_signal_start_in_file(path: &str, marker_size: usize) -> Option<usize> {
+    find_marker_in(File::open(path).unwrap(), marker_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markers_of_the_samples() {
+        let samples = [
+            ("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 7, 19),
+            ("bvwbjplbgvbhsrlpgdmjqwftvncz", 5, 23),
+            ("nppdvjthqldpwncqszvftbrmjlhg", 6, 23),
+            ("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 10, 29),
+            ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 11, 26),
+        ];
+        for (stream, packet, message) in samples {
+            assert_eq!(find_marker(stream.bytes(), 4), Some(packet));
+            assert_eq!(find_marker_in(stream.as_bytes(), 14), Some(message));
         }
     }
-    0
+
+    #[test]
+    fn all_markers() {
+        let positions: Vec<usize> = markers("abcabd".bytes(), 3).collect();
+        assert_eq!(positions, [3, 4, 5, 6]);
+        assert_eq!(find_marker("aaaa".bytes(), 2), None);
+    }
+
+    #[test]
+    fn other_bytes_start_a_new_window() {
+        assert_eq!(find_marker("ab1cd".bytes(), 3), None);
+        assert_eq!(find_marker("ab1cde".bytes(), 3), Some(6));
+        assert_eq!(find_marker("aAbcd".bytes(), 4), None);
+        assert_eq!(find_marker("xyz\nw".bytes(), 3), Some(3));
+        assert_eq!(find_marker_in("ab cde".as_bytes(), 3), None);
+    }
 }
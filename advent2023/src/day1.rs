@@ -1,67 +1,252 @@
+use std::cmp::{max_by_key, min_by_key, Reverse};
+use std::collections::{HashMap, VecDeque};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
+use aoc::digits::concat;
 use camino::Utf8Path as Path;
 
-const NUMBERS: [&str; 10] = [
-    "ZERO", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+const DIGITS: [&str; 10] = ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"];
+
+const ENGLISH: [&str; 9] = [
+    "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
 ];
 
-fn part1(input: &Path) -> usize {
-    let mut result = 0;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Match {
+    /// byte offset of the first character
+    start: usize,
+    len: usize,
+    value: usize,
+}
 
-    let reader = BufReader::new(File::open(input).unwrap());
-    for line in reader.lines() {
-        let line = line.unwrap();
-        let mut left: Option<usize> = None;
-        let mut right = 0;
-        for c in line.chars() {
-            if let Some(n) = c.to_digit(10) {
-                right = n as usize;
-                if left.is_none() {
-                    left.replace(n as usize);
-                }
+/// Aho–Corasick automaton that finds all (also overlapping) occurrences of a set of words in a
+/// single pass over the text
+#[derive(Debug)]
+struct Matcher {
+    /// trie edges
+    children: Vec<HashMap<u8, usize>>,
+    /// the node of the longest proper suffix that is also in the trie
+    fail: Vec<usize>,
+    /// words that end at a node: `(length, value)`
+    outputs: Vec<Vec<(usize, usize)>>,
+}
+
+impl Matcher {
+    fn new<'a, I: IntoIterator<Item = (&'a str, usize)>>(vocabulary: I) -> Self {
+        let mut result = Self {
+            children: vec![HashMap::new()],
+            fail: vec![0],
+            outputs: vec![vec![]],
+        };
+        for (word, value) in vocabulary {
+            let mut node = 0;
+            for b in word.bytes() {
+                node = match result.children[node].get(&b) {
+                    Some(child) => *child,
+                    None => {
+                        let child = result.children.len();
+                        result.children.push(HashMap::new());
+                        result.fail.push(0);
+                        result.outputs.push(vec![]);
+                        result.children[node].insert(b, child);
+                        child
+                    }
+                };
             }
+            result.outputs[node].push((word.len(), value));
         }
-        let sum = left.unwrap_or(0) * 10 + right;
-        result += sum;
+        result.link_failures();
+        result
     }
-    result
-}
 
-fn part2(input: &Path) -> usize {
-    let mut result = 0;
+    /// breadth first, so that the failure links of shorter prefixes are always known
+    fn link_failures(&mut self) {
+        let mut queue: VecDeque<usize> = self.children[0].values().copied().collect();
+        while let Some(node) = queue.pop_front() {
+            let edges: Vec<(u8, usize)> = self.children[node]
+                .iter()
+                .map(|(b, child)| (*b, *child))
+                .collect();
+            for (b, child) in edges {
+                let fail = self.step(self.fail[node], b);
+                self.fail[child] = fail;
+                let inherited = self.outputs[fail].clone();
+                self.outputs[child].extend(inherited);
+                queue.push_back(child);
+            }
+        }
+    }
 
-    let reader = BufReader::new(File::open(input).unwrap());
-    for line in reader.lines() {
-        let line = line.unwrap();
-        let mut left: Option<usize> = None;
-        let mut right = 0;
-        let mut slice = line.as_str();
-        while let Some(c) = slice.chars().next() {
-            if let Some(n) = c.to_digit(10) {
-                right = n as usize;
-                if left.is_none() {
-                    left.replace(n as usize);
-                }
-            } else {
-                for (i, number) in NUMBERS.iter().enumerate() {
-                    if slice.starts_with(number) {
-                        right = i;
-                        if left.is_none() {
-                            left.replace(i);
-                        }
-                    }
-                }
+    fn step(&self, mut node: usize, b: u8) -> usize {
+        loop {
+            if let Some(child) = self.children[node].get(&b) {
+                return *child;
             }
-            slice = &slice[1..];
+            if node == 0 {
+                return 0;
+            }
+            node = self.fail[node];
         }
-        let sum = left.unwrap_or(0) * 10 + right;
-        result += sum;
     }
-    result
+
+    /// all matches, ordered by their end
+    fn find_all<'a>(&'a self, text: &'a str) -> impl Iterator<Item = Match> + 'a {
+        let mut node = 0;
+        text.bytes().enumerate().flat_map(move |(i, b)| {
+            node = self.step(node, b);
+            self.outputs[node].iter().map(move |(len, value)| Match {
+                start: i + 1 - len,
+                len: *len,
+                value: *value,
+            })
+        })
+    }
+
+    /// the match that starts first and the one that starts last (the longer one on ties)
+    fn first_and_last(&self, text: &str) -> Option<(Match, Match)> {
+        self.find_all(text).fold(None, |result, m| match result {
+            None => Some((m, m)),
+            Some((first, last)) => Some((
+                min_by_key(first, m, |m| (m.start, Reverse(m.len))),
+                max_by_key(last, m, |m| (m.start, m.len)),
+            )),
+        })
+    }
+}
+
+/// The first and the last number of every line glued together. Lines without any number are an
+/// error.
+///
+/// Numbers are glued as decimals, so a word with a multi-digit value contributes all of its
+/// digits: with `("ten", 10)` in the vocabulary, `ten2` is 102 and `3ten` is 310.
+fn calibration_sum<R: BufRead>(reader: R, matcher: &Matcher) -> Result<usize, String> {
+    let mut result = 0;
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.unwrap();
+        let (first, last) = matcher
+            .first_and_last(&line)
+            .ok_or_else(|| format!("line {}: no digits in {line:?}", i + 1))?;
+        result += concat(first.value, last.value)
+            .ok_or_else(|| format!("line {}: calibration value is too large", i + 1))?;
+    }
+    Ok(result)
+}
+
+fn part1<R: BufRead>(reader: R) -> usize {
+    let matcher = Matcher::new(DIGITS.into_iter().zip(0..));
+    calibration_sum(reader, &matcher).unwrap_or_else(|err| panic!("{err}"))
+}
+
+fn part2<R: BufRead>(reader: R) -> usize {
+    let matcher = Matcher::new(
+        DIGITS
+            .into_iter()
+            .zip(0..)
+            .chain(ENGLISH.into_iter().zip(1..)),
+    );
+    calibration_sum(reader, &matcher).unwrap_or_else(|err| panic!("{err}"))
 }
 
 pub fn main(input: &Path) -> (usize, usize) {
-    (part1(input), part2(input))
+    let open = || BufReader::new(File::open(input).unwrap());
+    (part1(open()), part2(open()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sum(input: &str, matcher: &Matcher) -> Result<usize, String> {
+        calibration_sum(input.as_bytes(), matcher)
+    }
+
+    #[test]
+    fn samples() {
+        let sample1 = "1abc2\npqr3stu8vwx\na1b2c3d4e5f\ntreb7uchet\n";
+        assert_eq!(part1(sample1.as_bytes()), 142);
+        let sample2 = "\
+two1nine
+eightwothree
+abcone2threexyz
+xtwone3four
+4nineeightseven2
+zoneight234
+7pqrstsixteen
+";
+        assert_eq!(part2(sample2.as_bytes()), 281);
+    }
+
+    #[test]
+    fn overlapping_words() {
+        let matcher = Matcher::new(ENGLISH.into_iter().zip(1..));
+        let matches: Vec<Match> = matcher.find_all("eightwone").collect();
+        assert_eq!(
+            matches,
+            [
+                Match {
+                    start: 0,
+                    len: 5,
+                    value: 8
+                },
+                Match {
+                    start: 4,
+                    len: 3,
+                    value: 2
+                },
+                Match {
+                    start: 6,
+                    len: 3,
+                    value: 1
+                },
+            ]
+        );
+        assert_eq!(sum("eightwo", &matcher), Ok(82));
+        assert_eq!(sum("oneight\nsevenine", &matcher), Ok(18 + 79));
+    }
+
+    #[test]
+    fn other_vocabularies() {
+        let german = [
+            "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun",
+        ];
+        let matcher = Matcher::new(german.into_iter().zip(1..));
+        // offsets are in bytes, and zwei and eins overlap
+        let last = matcher.first_and_last("xfünfzweins").unwrap().1;
+        assert_eq!((last.start, last.value), (8, 1));
+        assert_eq!(sum("xfünfzweins\nachtzehn", &matcher), Ok(51 + 88));
+        assert_eq!(
+            sum("one", &matcher),
+            Err(String::from("line 1: no digits in \"one\""))
+        );
+
+        // the longer word wins if two of them start at the same position
+        let matcher = Matcher::new([("seven", 7), ("seventeen", 17)]);
+        assert_eq!(sum("seventeen", &matcher), Ok(1717));
+        assert_eq!(sum("sevenseventeen", &matcher), Ok(717));
+    }
+
+    #[test]
+    fn multi_digit_values() {
+        let matcher = Matcher::new(DIGITS.into_iter().zip(0..).chain([("ten", 10)]));
+        assert_eq!(sum("ten2", &matcher), Ok(102));
+        assert_eq!(sum("3ten", &matcher), Ok(310));
+        assert_eq!(sum("xtenx", &matcher), Ok(1010));
+
+        let matcher = Matcher::new([("max", usize::MAX)]);
+        assert_eq!(
+            sum("max", &matcher),
+            Err(String::from("line 1: calibration value is too large"))
+        );
+    }
+
+    #[test]
+    fn lines_without_digits() {
+        let matcher = Matcher::new(DIGITS.into_iter().zip(0..));
+        assert_eq!(
+            sum("1abc2\nabc\n", &matcher),
+            Err(String::from("line 2: no digits in \"abc\""))
+        );
+        assert_eq!(sum("", &matcher), Ok(0));
+    }
 }
@@ -1,101 +1,213 @@
-use std::cmp::max;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
 use camino::Utf8Path as Path;
 
-#[derive(Debug, Default)]
-struct RGB {
-    red: usize,
-    green: usize,
-    blue: usize,
+/// A number of cubes per colour. Colours that are not mentioned count as zero.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Cubes {
+    counts: BTreeMap<String, usize>,
 }
 
-#[derive(Debug, Default)]
-struct Draws {
-    game: usize,
-    cube_draws: Vec<RGB>,
-}
+impl Cubes {
+    pub fn get(&self, color: &str) -> usize {
+        self.counts.get(color).copied().unwrap_or(0)
+    }
 
-fn parse_line(line: &str) -> Draws {
-    let mut result = Draws::default();
-    if let Some((game, cubes)) = line.split_once(':') {
-        result.game = if let Some((_, n)) = game.split_once(' ') {
-            n.parse().unwrap()
-        } else {
-            panic!("could not read game number");
-        };
-
-        for round in cubes.split(';') {
-            let mut rgb = RGB::default();
-            for color_count in round.split(',') {
-                let color_count = color_count.trim();
-                if let Some((count, color_name)) = color_count.split_once(' ') {
-                    let count: usize = count.parse().unwrap();
-                    match color_name {
-                        "red" => {
-                            rgb.red = count;
-                        }
-                        "green" => {
-                            rgb.green = count;
-                        }
-                        "blue" => {
-                            rgb.blue = count;
-                        }
-                        _ => {
-                            panic!("Strange color: {color_name}")
-                        }
-                    }
-                } else {
-                    panic!("Could not split count and name");
-                }
-            }
-            result.cube_draws.push(rgb);
+    /// could these cubes have been drawn from `bag`?
+    pub fn fit_into(&self, bag: &Cubes) -> bool {
+        self.counts
+            .iter()
+            .all(|(color, count)| *count <= bag.get(color))
+    }
+
+    /// the smallest set of cubes that contains both
+    pub fn union(&self, other: &Cubes) -> Cubes {
+        let mut result = self.clone();
+        for (color, count) in &other.counts {
+            let entry = result.counts.entry(color.clone()).or_default();
+            *entry = (*entry).max(*count);
         }
-    } else {
-        panic!("Could not split line");
+        result
+    }
+
+    /// product of the counts of the given colours
+    pub fn power<'a, I: IntoIterator<Item = &'a str>>(&self, colors: I) -> usize {
+        colors.into_iter().map(|color| self.get(color)).product()
+    }
+
+    pub fn colors(&self) -> impl Iterator<Item = &str> {
+        self.counts.keys().map(String::as_str)
     }
-    result
 }
 
-fn part1(input: &Path) -> usize {
-    let mut result = 0;
-    let max_red = 12;
-    let max_green = 13;
-    let max_blue = 14;
+/// lists like `3 blue, 4 red`
+impl std::str::FromStr for Cubes {
+    type Err = String;
 
-    let reader = BufReader::new(File::open(input).unwrap());
-    for line in reader.lines() {
-        let game = parse_line(&line.unwrap());
-        let realistic = game.cube_draws.iter().all(|round| {
-            round.red <= max_red && round.green <= max_green && round.blue <= max_blue
-        });
-        if realistic {
-            result += game.game;
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut result = Cubes::default();
+        for color_count in s.split(',') {
+            let color_count = color_count.trim();
+            let (count, color) = color_count
+                .split_once(' ')
+                .ok_or_else(|| format!("could not split count and name: {color_count}"))?;
+            let count: usize = count.parse().map_err(|_| format!("bad count: {count}"))?;
+            *result.counts.entry(color.trim().to_string()).or_default() += count;
         }
+        Ok(result)
     }
-    result
 }
 
-fn part2(input: &Path) -> usize {
-    let mut result = 0;
+#[derive(Debug, Default)]
+struct Game {
+    id: usize,
+    draws: Vec<Cubes>,
+}
+
+impl Game {
+    fn is_possible(&self, bag: &Cubes) -> bool {
+        self.draws.iter().all(|draw| draw.fit_into(bag))
+    }
+
+    fn minimum_bag(&self) -> Cubes {
+        self.draws
+            .iter()
+            .fold(Cubes::default(), |bag, draw| bag.union(draw))
+    }
+}
+
+fn parse_line(line: &str) -> Result<Game, String> {
+    let (game, draws) = line.split_once(':').ok_or("could not split line")?;
+    let id = game
+        .trim()
+        .strip_prefix("Game ")
+        .and_then(|n| n.parse().ok())
+        .ok_or("could not read game number")?;
+    let draws = draws.split(';').map(str::parse).collect::<Result<_, _>>()?;
+    Ok(Game { id, draws })
+}
+
+fn read_games(input: &Path) -> Vec<Game> {
     let reader = BufReader::new(File::open(input).unwrap());
-    for line in reader.lines() {
-        let game = parse_line(&line.unwrap());
-        let mut red = 0;
-        let mut green = 0;
-        let mut blue = 0;
-        for draw in game.cube_draws {
-            red = max(red, draw.red);
-            green = max(green, draw.green);
-            blue = max(blue, draw.blue);
-        }
-        let power = red * green * blue;
-        result += power;
+    reader
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            parse_line(&line.unwrap()).unwrap_or_else(|err| panic!("line {}: {err}", i + 1))
+        })
+        .collect()
+}
+
+/// the bag from the puzzle description
+pub fn default_bag() -> Cubes {
+    "12 red, 13 green, 14 blue".parse().unwrap()
+}
+
+/// The bag can be configured in `day2.bag` next to the input, e.g. `12 red, 13 green, 14 blue`
+fn configured_bag(input: &Path) -> Cubes {
+    match std::fs::read_to_string(input.with_file_name("day2.bag")) {
+        Ok(config) => config
+            .trim()
+            .parse()
+            .unwrap_or_else(|err| panic!("bad bag config: {err}")),
+        Err(_) => default_bag(),
     }
-    result
+}
+
+fn part1(games: &[Game], bag: &Cubes) -> usize {
+    games
+        .iter()
+        .filter(|game| game.is_possible(bag))
+        .map(|game| game.id)
+        .sum()
+}
+
+/// The power covers every colour that shows up in any game, so a game in which one of them is
+/// missing has a power of zero. The bag plays no role here.
+fn part2(games: &[Game]) -> usize {
+    let colors: BTreeSet<&str> = games
+        .iter()
+        .flat_map(|game| game.draws.iter().flat_map(Cubes::colors))
+        .collect();
+    games
+        .iter()
+        .map(|game| game.minimum_bag().power(colors.iter().copied()))
+        .sum()
+}
+
+pub fn main_with_bag(input: &Path, bag: &Cubes) -> (usize, usize) {
+    let games = read_games(input);
+    (part1(&games, bag), part2(&games))
 }
 
 pub fn main(input: &Path) -> (usize, usize) {
-    (part1(input), part2(input))
+    main_with_bag(input, &configured_bag(input))
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+
+    use super::*;
+
+    const SAMPLE: &str = "\
+Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
+Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
+Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
+Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green
+";
+
+    /// a directory of its own for every test, so that `day2.bag` files don't get in the way
+    fn write_input(test: &str, games: &str, bag: Option<&str>) -> Utf8PathBuf {
+        let dir = std::env::temp_dir().join(format!("advent2023-day2-{test}"));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir = Utf8PathBuf::from_path_buf(dir).unwrap();
+        std::fs::write(dir.join("day2.txt"), games).unwrap();
+        match bag {
+            Some(bag) => std::fs::write(dir.join("day2.bag"), bag).unwrap(),
+            None => {
+                let _ = std::fs::remove_file(dir.join("day2.bag"));
+            }
+        }
+        dir.join("day2.txt")
+    }
+
+    #[test]
+    fn sample() {
+        let input = write_input("sample", SAMPLE, None);
+        assert_eq!(main(&input), (8, 2286));
+    }
+
+    #[test]
+    fn custom_bag() {
+        let input = write_input("custom", SAMPLE, None);
+        let bag: Cubes = "20 red, 13 green, 15 blue".parse().unwrap();
+        assert_eq!(main_with_bag(&input, &bag), (1 + 2 + 3 + 4 + 5, 2286));
+        // the bag doesn't change the power, even without blue cubes
+        let bag: Cubes = "12 red, 13 green".parse().unwrap();
+        assert_eq!(main_with_bag(&input, &bag), (0, 2286));
+    }
+
+    #[test]
+    fn bag_file() {
+        let input = write_input("file", SAMPLE, Some("20 red, 13 green, 15 blue\n"));
+        assert_eq!(main(&input), (15, 2286));
+    }
+
+    #[test]
+    fn other_colors() {
+        let games = "\
+Game 1: 2 cyan, 1 magenta; 3 cyan
+Game 2: 1 magenta, 4 yellow, 1 cyan
+";
+        let input = write_input("other", games, Some("3 cyan, 1 magenta, 4 yellow"));
+        // game 1 has no yellow cubes
+        assert_eq!(main(&input), (3, 4));
+        let bag: Cubes = "2 cyan, 9 magenta".parse().unwrap();
+        assert_eq!(main_with_bag(&input, &bag), (0, 4));
+        assert_eq!(bag.get("yellow"), 0);
+    }
 }
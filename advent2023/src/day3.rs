@@ -3,156 +3,196 @@ use std::{
     io::{BufRead, BufReader},
 };
 
+use aoc::digits::from_digits;
 use camino::Utf8Path as Path;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Position {
     x: usize,
     y: usize,
 }
 
 #[derive(Debug)]
-struct PotentialPartNumber {
-    n: usize,
+struct Symbol {
+    c: char,
     pos: Position,
 }
 
-impl PotentialPartNumber {
-    fn new(n: usize, x: usize, y: usize) -> Self {
-        let pos = Position { x, y };
-        Self { n, pos }
-    }
-
-    fn is_activated_by(&self, symbol_pos: &Position) -> bool {
-        if self.pos.y.abs_diff(symbol_pos.y) > 1 {
-            return false;
-        }
-        let left_x = self.pos.x.saturating_sub(1);
-        let right_x = self.pos.x.saturating_add(self.n.to_string().len());
-        left_x <= symbol_pos.x && symbol_pos.x <= right_x
-    }
+/// Every cell that is covered by a number knows the index of that number, so we can look up the
+/// neighbours of a symbol without looking at all numbers.
+#[derive(Debug, Default)]
+struct Schematic {
+    numbers: Vec<usize>,
+    symbols: Vec<Symbol>,
+    cells: Vec<Vec<Option<usize>>>,
 }
 
-fn make_number(digits: &[u8]) -> usize {
-    let mut result = 0;
-    for digit in digits {
-        result *= 10;
-        result += *digit as usize;
+impl Schematic {
+    fn read(input: &Path) -> Self {
+        Self::from_reader(BufReader::new(File::open(input).unwrap()))
     }
-    result
-}
 
-fn read_numbers(input: &Path) -> Vec<PotentialPartNumber> {
-    let mut result = vec![];
-    let reader = BufReader::new(File::open(input).unwrap());
-    for (y, line) in reader.lines().enumerate() {
-        let line = line.unwrap();
-        let mut start: Option<usize> = None;
-        let mut digits: Vec<u8> = vec![];
-        for (x, c) in line.chars().enumerate() {
-            if let Some(digit) = c.to_digit(10) {
-                if start.is_none() {
-                    start = Some(x);
+    fn from_reader<R: BufRead>(reader: R) -> Self {
+        let mut result = Self::default();
+        for (y, line) in reader.lines().enumerate() {
+            let line = line.unwrap();
+            let mut row = Vec::with_capacity(line.len());
+            let mut digits: Vec<u8> = vec![];
+            for (x, c) in line.trim_end().chars().enumerate() {
+                if let Some(digit) = c.to_digit(10) {
+                    digits.push(digit as u8);
+                    row.push(Some(result.numbers.len()));
+                    continue;
+                }
+                result.finish_number(&mut digits);
+                row.push(None);
+                if c != '.' {
+                    let pos = Position { x, y };
+                    result.symbols.push(Symbol { c, pos });
                 }
-                digits.push(digit as u8);
-            } else if let Some(x) = start {
-                let number = PotentialPartNumber::new(make_number(&digits), x, y);
-                digits.clear();
-                result.push(number);
-                start = None;
             }
+            result.finish_number(&mut digits);
+            result.cells.push(row);
         }
-        if let Some(x) = start {
-            let number = PotentialPartNumber::new(make_number(&digits), x, y);
-            result.push(number);
+        result
+    }
+
+    /// add the number whose digits have been collected so far
+    fn finish_number(&mut self, digits: &mut Vec<u8>) {
+        if !digits.is_empty() {
+            self.numbers.push(from_digits(digits.drain(..)));
         }
     }
-    result
-}
 
-fn gather_positions<F>(input: &Path, condition: F) -> Vec<Position>
-where
-    F: Fn(char) -> bool,
-{
-    let mut result = vec![];
-    let reader = BufReader::new(File::open(input).unwrap());
-    for (y, line) in reader.lines().enumerate() {
-        let line = line.unwrap();
-        for (x, c) in line.chars().enumerate() {
-            if condition(c) {
-                result.push(Position { x, y });
+    fn number_at(&self, x: usize, y: usize) -> Option<usize> {
+        *self.cells.get(y)?.get(x)?
+    }
+
+    /// indices of the distinct numbers around a position
+    fn adjacent_numbers(&self, pos: Position) -> Vec<usize> {
+        let mut result = vec![];
+        for y in pos.y.saturating_sub(1)..=pos.y + 1 {
+            for x in pos.x.saturating_sub(1)..=pos.x + 1 {
+                if let Some(i) = self.number_at(x, y) {
+                    // a number can cover up to three neighbouring cells of the same row
+                    if !result.contains(&i) {
+                        result.push(i);
+                    }
+                }
             }
         }
+        result
+    }
+
+    /// every symbol together with the numbers around it
+    fn symbol_neighbours(&self) -> impl Iterator<Item = (&Symbol, Vec<usize>)> {
+        self.symbols.iter().map(|symbol| {
+            let numbers = self
+                .adjacent_numbers(symbol.pos)
+                .into_iter()
+                .map(|i| self.numbers[i])
+                .collect();
+            (symbol, numbers)
+        })
+    }
+
+    /// symbols `c` with exactly `n` numbers around them
+    fn gears(&self, c: char, n: usize) -> impl Iterator<Item = Vec<usize>> + '_ {
+        self.symbol_neighbours()
+            .filter(move |(symbol, numbers)| symbol.c == c && numbers.len() == n)
+            .map(|(_, numbers)| numbers)
     }
-    result
-}
 
-fn gather_activating_positions(input: &Path) -> Vec<Position> {
-    gather_positions(input, |c| !(c.is_ascii_digit() || c == '.'))
-    /*
-    let mut result = vec![];
-    let reader = BufReader::new(File::open(input).unwrap());
-    for (y, line) in reader.lines().enumerate() {
-        let line = line.unwrap();
-        for (x, c) in line.chars().enumerate() {
-            if !(c.is_ascii_digit() || c == '.') {
-                result.push(Position { x, y });
+    /// numbers next to at least one symbol
+    fn part_numbers(&self) -> Vec<usize> {
+        let mut is_part = vec![false; self.numbers.len()];
+        for symbol in &self.symbols {
+            for i in self.adjacent_numbers(symbol.pos) {
+                is_part[i] = true;
             }
         }
+        self.numbers
+            .iter()
+            .zip(is_part)
+            .filter_map(|(number, is_part)| is_part.then_some(*number))
+            .collect()
     }
-    result
-    */
 }
 
-fn gather_gears(input: &Path) -> Vec<Position> {
-    gather_positions(input, |c| c == '*')
+fn part1(schematic: &Schematic) -> usize {
+    schematic.part_numbers().iter().sum()
 }
 
-fn part1(input: &Path) -> usize {
-    let potential_part_numbers = read_numbers(input);
-    let activators = gather_activating_positions(input);
+fn part2(schematic: &Schematic) -> usize {
+    schematic
+        .gears('*', 2)
+        .map(|numbers| numbers.iter().product::<usize>())
+        .sum()
+}
 
-    // let tester = PotentialPartNumber::new(421, 2, 2);
-    // assert!(tester.is_activated_by(&Position { x: 6, y: 1 }));
+pub fn main(input: &Path) -> (usize, usize) {
+    let schematic = Schematic::read(input);
+    (part1(&schematic), part2(&schematic))
+}
 
-    let active_filter = |part_number: &PotentialPartNumber| {
-        if activators
-            .iter()
-            .any(|activator| part_number.is_activated_by(activator))
-        {
-            Some(part_number.n)
-        } else {
-            None
-        }
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+467..114..
+...*......
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598..
+";
+
+    fn parse(input: &str) -> Schematic {
+        Schematic::from_reader(input.as_bytes())
+    }
 
-    // dbg!(potential_part_numbers.iter().map(|no| no.n).sum::<usize>());
-    potential_part_numbers
-        .iter()
-        .filter_map(active_filter)
-        .sum()
-}
+    #[test]
+    fn sample() {
+        let schematic = parse(SAMPLE);
+        assert_eq!(schematic.numbers.len(), 10);
+        assert_eq!(schematic.symbols.len(), 6);
+        assert_eq!(
+            schematic.part_numbers(),
+            [467, 35, 633, 617, 592, 755, 664, 598]
+        );
+        assert_eq!(part1(&schematic), 4361);
+        assert_eq!(part2(&schematic), 467835);
+    }
 
-fn part2(input: &Path) -> usize {
-    let mut result = 0;
-    let potential_part_numbers = read_numbers(input); // a bit wasteful to not reuse result
-    let gears = gather_gears(input);
-    for gear in gears {
-        for (i, n1) in potential_part_numbers.iter().enumerate() {
-            if !n1.is_activated_by(&gear) {
-                continue;
-            }
-            for n2 in potential_part_numbers.iter().skip(i + 1) {
-                if n2.is_activated_by(&gear) {
-                    let gear_ratio = n1.n * n2.n;
-                    result += gear_ratio;
-                }
-            }
-        }
+    #[test]
+    fn gears_with_n_numbers() {
+        // the star touches three numbers (12 with both digits) and the hash only touches one
+        let schematic = parse(
+            "\
+12.3
+.*..
+4.56
+#...
+",
+        );
+        let gears: Vec<Vec<usize>> = schematic.gears('*', 3).collect();
+        assert_eq!(gears, [vec![12, 4, 56]]);
+        assert_eq!(schematic.gears('*', 2).count(), 0);
+        assert_eq!(part2(&schematic), 0);
+        assert_eq!(schematic.gears('#', 1).collect::<Vec<_>>(), [vec![4]]);
+        assert_eq!(part1(&schematic), 12 + 4 + 56);
     }
-    result
-}
 
-pub fn main(input: &Path) -> (usize, usize) {
-    (part1(input), part2(input))
+    #[test]
+    fn numbers_at_the_end_of_lines() {
+        let schematic = parse("..12\n*..+\n...7\n5...\n");
+        assert_eq!(schematic.numbers, [12, 7, 5]);
+        assert_eq!(schematic.part_numbers(), [12, 7]);
+        assert_eq!(schematic.gears('+', 2).collect::<Vec<_>>(), [vec![12, 7]]);
+    }
 }
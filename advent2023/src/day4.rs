@@ -1,20 +1,22 @@
 use std::{
     cmp::Ordering,
-    collections::VecDeque,
     fs::File,
     io::{BufRead, BufReader},
+    ops::RangeInclusive,
 };
 
 use camino::Utf8Path as Path;
 
 #[derive(Debug)]
 struct Card {
-    hits: usize,
+    id: usize,
+    /// the winning numbers that we have
+    matches: Vec<usize>,
 }
 
 impl Card {
-    fn new(winning: Vec<usize>, given: Vec<usize>) -> Self {
-        let mut hits = 0;
+    fn new(id: usize, winning: Vec<usize>, given: Vec<usize>) -> Self {
+        let mut matches = vec![];
         let mut givens = given.iter().peekable();
         // sure, I could just have used a HashSet...
         // but this is more fun :-)
@@ -25,7 +27,7 @@ impl Card {
                         givens.next();
                     }
                     Ordering::Equal => {
-                        hits += 1;
+                        matches.push(*given);
                         givens.next();
                     }
                     Ordering::Greater => break,
@@ -33,7 +35,16 @@ impl Card {
             }
         }
 
-        Self { hits }
+        Self { id, matches }
+    }
+
+    fn hits(&self) -> usize {
+        self.matches.len()
+    }
+
+    /// ids of the cards that we win a copy of
+    fn prizes(&self) -> RangeInclusive<usize> {
+        self.id + 1..=self.id + self.hits()
     }
 }
 
@@ -46,7 +57,8 @@ fn points(matches: usize) -> usize {
 }
 
 fn extract_numbers(line: &str) -> Card {
-    let (_, rest) = line.split_once(": ").unwrap();
+    let (name, rest) = line.split_once(": ").unwrap();
+    let id = name.trim_start_matches("Card").trim().parse().unwrap();
     let (lhs, rhs) = rest.split_once(" | ").unwrap();
     let lhs = lhs.split(' ');
     let rhs = rhs.split(' ');
@@ -54,51 +66,112 @@ fn extract_numbers(line: &str) -> Card {
     let mut given: Vec<usize> = rhs.filter_map(|n| n.parse().ok()).collect();
     winning.sort_unstable();
     given.sort_unstable();
-    Card::new(winning, given)
+    Card::new(id, winning, given)
 }
 
-fn part1(input: &Path) -> usize {
-    let mut result = 0;
+fn read_cards(input: &Path) -> Vec<Card> {
     let reader = BufReader::new(File::open(input).unwrap());
-    for line in reader.lines() {
-        let numbers = extract_numbers(&line.unwrap());
-        result += points(numbers.hits);
-    }
-    result
+    reader
+        .lines()
+        .map(|line| extract_numbers(&line.unwrap()))
+        .collect()
 }
 
-fn part2(input: &Path) -> usize {
-    let mut result = 0;
-    let num_lines = std::fs::read_to_string(input)
-        .unwrap()
-        .matches('\n')
-        .count()
-        + 1;
-
-    let cards = {
-        let mut cards = Vec::with_capacity(num_lines + 1);
-        cards.push(Card::new(vec![], vec![])); // dummy index 0
-        let reader = BufReader::new(File::open(input).unwrap());
-        for line in reader.lines() {
-            cards.push(extract_numbers(&line.unwrap()));
-        }
-        cards
-    };
-    let mut queue = VecDeque::with_capacity(2 * cards.len());
-    for i in 1..cards.len() {
-        // we automatically win all originals
-        queue.push_back(i);
-    }
-    while let Some(card_index) = queue.pop_front() {
-        result += 1;
-        let card = &cards[card_index];
-        for i in (card_index + 1)..(card_index + card.hits + 1) {
-            queue.push_back(i);
+/// For every card in the order of `cards`: the ids of the cards that won us copies of it, with
+/// the number of copies that each of them produced. The original card is not listed. The copies
+/// of a card only depend on the cards before it, so a single pass is enough.
+fn copy_sources(cards: &[Card]) -> Vec<Vec<(usize, u64)>> {
+    let first_id = cards.first().map_or(0, |card| card.id);
+    let mut sources: Vec<Vec<(usize, u64)>> = vec![vec![]; cards.len()];
+    for (i, card) in cards.iter().enumerate() {
+        let won = total_copies(&sources[i]);
+        for prize in card.prizes() {
+            if let Some(prize_sources) = sources.get_mut(prize - first_id) {
+                prize_sources.push((card.id, won));
+            }
         }
     }
-    result
+    sources
+}
+
+/// the copies from all sources plus the original
+fn total_copies(sources: &[(usize, u64)]) -> u64 {
+    1 + sources.iter().map(|(_, copies)| copies).sum::<u64>()
+}
+
+/// How many copies we end up with of every card, in the order of `cards`
+fn count_copies(cards: &[Card]) -> Vec<u64> {
+    copy_sources(cards)
+        .iter()
+        .map(|sources| total_copies(sources))
+        .collect()
+}
+
+fn part1(cards: &[Card]) -> usize {
+    cards.iter().map(|card| points(card.hits())).sum()
+}
+
+fn part2(cards: &[Card]) -> usize {
+    count_copies(cards).iter().sum::<u64>() as usize
 }
 
 pub fn main(input: &Path) -> (usize, usize) {
-    (part1(input), part2(input))
+    let cards = read_cards(input);
+    (part1(&cards), part2(&cards))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11
+";
+
+    fn parse(input: &str) -> Vec<Card> {
+        input.lines().map(extract_numbers).collect()
+    }
+
+    #[test]
+    fn sample() {
+        let cards = parse(SAMPLE);
+        let hits: Vec<usize> = cards.iter().map(Card::hits).collect();
+        assert_eq!(hits, [4, 2, 2, 1, 0, 0]);
+        assert_eq!(cards[0].matches, [17, 48, 83, 86]);
+        assert_eq!(cards[0].prizes(), 2..=5);
+        assert!(cards[5].prizes().is_empty());
+        assert_eq!(part1(&cards), 13);
+        assert_eq!(count_copies(&cards), [1, 2, 4, 8, 14, 1]);
+        assert_eq!(part2(&cards), 30);
+    }
+
+    #[test]
+    fn copies_by_source() {
+        let sources = copy_sources(&parse(SAMPLE));
+        assert_eq!(
+            sources,
+            [
+                vec![],
+                vec![(1, 1)],
+                vec![(1, 1), (2, 2)],
+                vec![(1, 1), (2, 2), (3, 4)],
+                vec![(1, 1), (3, 4), (4, 8)],
+                vec![],
+            ]
+        );
+    }
+
+    #[test]
+    fn prizes_past_the_last_card() {
+        // the table doesn't start at card 1 and card 8 would win copies of cards 9 and 10
+        let cards = parse("Card 7: 1 2 | 1 3\nCard 8: 1 2 | 2 1\n");
+        assert_eq!(copy_sources(&cards), [vec![], vec![(7, 1)]]);
+        assert_eq!(part2(&cards), 3);
+        assert_eq!(part1(&cards), 1 + 2);
+    }
 }
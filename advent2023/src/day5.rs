@@ -1,11 +1,7 @@
 use std::cmp::{max, min};
-use std::{
-    collections::HashMap,
-    fs::File,
-    io::{BufRead, BufReader},
-    ops::Range,
-};
+use std::{collections::HashMap, ops::Range};
 
+use aoc::paragraphs;
 use camino::Utf8Path as Path;
 
 const LOOKUPS: &[&str] = &[
@@ -32,46 +28,40 @@ struct Maps {
 }
 
 fn read_maps(input: &Path) -> Maps {
-    let reader = BufReader::new(File::open(input).unwrap());
-    let mut lines = reader.lines();
-    let seeds_line = lines.next().unwrap().unwrap();
-    let (_, seed_values) = seeds_line.split_once(": ").unwrap();
+    let mut blocks = paragraphs::from_file(input);
+    let seeds_block = blocks.next().unwrap();
+    let (_, seed_values) = seeds_block[0].split_once(": ").unwrap();
     let seeds: Vec<usize> = seed_values
         .trim()
         .split(' ')
         .map(|n| n.parse().unwrap())
         .collect();
-    let mut result = Maps::default();
-    result.seeds = seeds;
-    let mut from = String::new();
-    let mut to = String::new();
+    let mut result = Maps {
+        seeds,
+        ..Default::default()
+    };
 
-    lines.next();
-    while let Some(line) = lines.next() {
-        let line = line.unwrap();
-        let line = line.trim();
-        if let Some(map_name) = line.strip_suffix(" map:") {
-            let split = map_name.split_once("-to-").unwrap();
-            from = split.0.to_string();
-            to = split.1.to_string();
-        } else if !line.is_empty() {
-            let mut numbers: Vec<usize> = line.split(' ').map(|n| n.parse().unwrap()).collect();
+    for block in blocks {
+        let map_name = block[0].trim().strip_suffix(" map:").unwrap();
+        let (from, to) = map_name.split_once("-to-").unwrap();
+        let mut map = vec![];
+        for line in &block[1..] {
+            let mut numbers: Vec<usize> = line
+                .split_whitespace()
+                .map(|n| n.parse().unwrap())
+                .collect();
             let interval_length = numbers.pop().unwrap();
             let source = numbers.pop().unwrap();
             let destination = numbers.pop().unwrap();
-            let mapping = Mapping {
+            map.push(Mapping {
                 source: source..(source + interval_length),
                 destination: destination..(destination + interval_length),
-            };
-            result
-                .mappings
-                .entry((from.clone(), to.clone()))
-                .or_default()
-                .push(mapping);
+            });
         }
-    }
-    for map in result.mappings.values_mut() {
-        map.sort_unstable_by(|a, b| a.source.start.cmp(&b.source.start))
+        map.sort_unstable_by_key(|a| a.source.start);
+        result
+            .mappings
+            .insert((from.to_string(), to.to_string()), map);
     }
     result
 }
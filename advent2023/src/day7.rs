@@ -1,10 +1,11 @@
 use std::{
     cmp::Ordering,
-    collections::HashMap,
     fs::File,
     io::{BufRead, BufReader},
+    marker::PhantomData,
 };
 
+use aoc::counter::ArrayCounter;
 use camino::Utf8Path as Path;
 
 #[repr(u8)]
@@ -25,6 +26,14 @@ enum Card {
     A = 14,
 }
 
+impl From<Card> for usize {
+    fn from(card: Card) -> Self {
+        card as usize
+    }
+}
+
+type CardCounter = ArrayCounter<Card, 15>;
+
 #[derive(Debug)]
 struct BadCardError {}
 
@@ -32,249 +41,134 @@ impl TryFrom<char> for Card {
     type Error = BadCardError;
 
     fn try_from(value: char) -> Result<Self, Self::Error> {
-        if let Some(n) = value.to_digit(10) {
-            Ok(match n {
-                0 | 1 => panic!("bad input"),
-                2 => Card::N2,
-                3 => Card::N3,
-                4 => Card::N4,
-                5 => Card::N5,
-                6 => Card::N6,
-                7 => Card::N7,
-                8 => Card::N8,
-                9 => Card::N9,
-                _ => panic!("cannot happen"),
-            })
-        } else {
-            match value {
-                'T' => Ok(Card::T),
-                'J' => Ok(Card::J),
-                'Q' => Ok(Card::Q),
-                'K' => Ok(Card::K),
-                'A' => Ok(Card::A),
-                _ => Err(Self::Error {}),
-            }
+        match value {
+            '2' => Ok(Card::N2),
+            '3' => Ok(Card::N3),
+            '4' => Ok(Card::N4),
+            '5' => Ok(Card::N5),
+            '6' => Ok(Card::N6),
+            '7' => Ok(Card::N7),
+            '8' => Ok(Card::N8),
+            '9' => Ok(Card::N9),
+            'T' => Ok(Card::T),
+            'J' => Ok(Card::J),
+            'Q' => Ok(Card::Q),
+            'K' => Ok(Card::K),
+            'A' => Ok(Card::A),
+            _ => Err(Self::Error {}),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-#[repr(u8)]
-enum HandType {
-    HighCard,
-    OnePair,
-    TwoPair,
-    ThreeOfAKind,
-    FullHouse,
-    FourOfAKind,
-    FiveOfAKind,
+/// How a variant of the game values cards
+trait Rules {
+    /// wildcards pretend to be whatever card makes the hand strongest
+    fn is_wildcard(_card: Card) -> bool {
+        false
+    }
+
+    /// breaks ties between hands of the same type
+    fn strength(card: Card) -> u8 {
+        card as u8
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
-struct Hand {
-    cards: [Card; 5],
-    bid: usize,
-    hand_type: HandType,
+struct Standard;
+
+impl Rules for Standard {}
+
+/// `J` is a joker: a wildcard and the weakest card on its own
+#[derive(Debug, PartialEq, Eq)]
+struct Jokers;
+
+impl Rules for Jokers {
+    fn is_wildcard(card: Card) -> bool {
+        card == Card::J
+    }
+
+    fn strength(card: Card) -> u8 {
+        if card == Card::J {
+            1
+        } else {
+            card as u8
+        }
+    }
 }
 
-impl Hand {
-    fn new(cards_list: Vec<Card>, bid: usize) -> Self {
-        let mut cards = [Card::A; 5];
-        let counter = {
-            let mut counter = HashMap::<Card, usize>::new();
-            for (i, c) in cards_list.iter().enumerate() {
-                cards[i] = *c;
-                counter.entry(*c).and_modify(|c| *c += 1).or_insert(1);
-            }
-            counter
-        };
-        let mut counts: Vec<usize> = counter.values().cloned().collect();
-        counts.sort_unstable();
-        let hand_type = match counts.pop().unwrap() {
-            5 => HandType::FiveOfAKind,
-            4 => HandType::FourOfAKind,
-            3 => {
-                if counts.pop().unwrap() == 2 {
-                    HandType::FullHouse
-                } else {
-                    HandType::ThreeOfAKind
-                }
-            }
-            2 => {
-                if counts.pop().unwrap() == 2 {
-                    HandType::TwoPair
-                } else {
-                    HandType::OnePair
-                }
-            }
-            1 => HandType::HighCard,
-            _ => panic!("should not happen"),
-        };
-        Self {
-            cards,
-            bid,
-            hand_type,
+/// The sizes of all groups of equal cards in descending order. Ordering by them gives the usual
+/// ranking of high card < one pair < two pair < three of a kind < full house < four of a kind <
+/// five of a kind, and it works for hands of any size, e.g. three pairs beat two pairs.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct HandType(Vec<usize>);
+
+impl HandType {
+    fn classify<R: Rules>(cards: &[Card]) -> Self {
+        let counter: CardCounter = cards
+            .iter()
+            .filter(|card| !R::is_wildcard(**card))
+            .copied()
+            .collect();
+        let wildcards = cards.len() - counter.total();
+        let mut counts = counter.signature();
+        // the wildcards are always best spent on the largest group
+        match counts.first_mut() {
+            Some(largest) => *largest += wildcards,
+            None => counts.push(wildcards),
         }
+        Self(counts)
     }
 }
 
 #[derive(Debug, PartialEq, Eq)]
-struct JokerHand {
-    cards: [Card; 5],
+struct Hand<R> {
+    cards: Vec<Card>,
     bid: usize,
     hand_type: HandType,
+    rules: PhantomData<R>,
 }
 
-impl JokerHand {
-    fn new(cards_list: Vec<Card>, bid: usize) -> Self {
-        let mut cards = [Card::A; 5];
-        let mut jokers = 0;
-        let counter = {
-            let mut counter = HashMap::<Card, usize>::new();
-            for (i, c) in cards_list.iter().enumerate() {
-                cards[i] = *c;
-                if *c == Card::J {
-                    jokers += 1;
-                } else {
-                    counter.entry(*c).and_modify(|c| *c += 1).or_insert(1);
-                }
-            }
-            counter
-        };
-        let mut counts: Vec<usize> = counter.values().cloned().collect();
-        counts.sort_unstable();
-
-        let hand_type = {
-            if let Some(highest_count) = counts.pop() {
-                match highest_count {
-                    5 => HandType::FiveOfAKind,
-                    4 => {
-                        if jokers == 1 {
-                            HandType::FiveOfAKind
-                        } else {
-                            HandType::FourOfAKind
-                        }
-                    }
-                    3 => {
-                        if let Some(next_highest) = counts.pop() {
-                            match next_highest {
-                                2 => HandType::FullHouse,
-                                1 => match jokers {
-                                    1 => HandType::FourOfAKind,
-                                    0 => HandType::ThreeOfAKind,
-                                    _ => panic!("cannot happen"),
-                                },
-                                _ => panic!("cannot happen"),
-                            }
-                        } else {
-                            assert_eq!(jokers, 2);
-                            HandType::FiveOfAKind
-                        }
-                    }
-                    2 => {
-                        if let Some(next_highest) = counts.pop() {
-                            match next_highest {
-                                2 => {
-                                    if jokers == 1 {
-                                        HandType::FullHouse
-                                    } else {
-                                        HandType::TwoPair
-                                    }
-                                }
-                                1 => match jokers {
-                                    2 => HandType::FourOfAKind,
-                                    1 => HandType::ThreeOfAKind,
-                                    0 => HandType::OnePair,
-                                    _ => panic!("cannot happen"),
-                                },
-                                _ => panic!("cannot happen"),
-                            }
-                        } else {
-                            assert_eq!(jokers, 3);
-                            HandType::FiveOfAKind
-                        }
-                    }
-                    1 => match jokers {
-                        4 => HandType::FiveOfAKind,
-                        3 => HandType::FourOfAKind,
-                        2 => HandType::ThreeOfAKind,
-                        1 => HandType::OnePair,
-                        0 => HandType::HighCard,
-                        _ => panic!("impossible"),
-                    },
-                    _ => panic!("should not happen"),
-                }
-            } else {
-                HandType::FiveOfAKind // 5 jokers
-            }
-        };
+impl<R: Rules> Hand<R> {
+    fn new(cards: Vec<Card>, bid: usize) -> Self {
+        let hand_type = HandType::classify::<R>(&cards);
         Self {
             cards,
             bid,
             hand_type,
+            rules: PhantomData,
         }
     }
 }
 
-impl PartialOrd for Hand {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+impl<R: Rules + Eq> PartialOrd for Hand<R> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl Ord for Hand {
+impl<R: Rules + Eq> Ord for Hand<R> {
     fn cmp(&self, other: &Self) -> Ordering {
-        let result = self.hand_type.cmp(&other.hand_type);
-        if result == Ordering::Equal {
-            for (card_a, card_b) in self.cards.iter().zip(other.cards.iter()) {
-                let result = card_a.cmp(card_b);
-                if result != Ordering::Equal {
-                    return result;
-                }
-            }
-        }
-        result
-    }
-}
-
-impl PartialOrd for JokerHand {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+        self.hand_type.cmp(&other.hand_type).then_with(|| {
+            let strengths = |hand: &Self| {
+                hand.cards
+                    .iter()
+                    .map(|card| R::strength(*card))
+                    .collect::<Vec<_>>()
+            };
+            strengths(self).cmp(&strengths(other))
+        })
     }
 }
 
-impl Ord for JokerHand {
-    fn cmp(&self, other: &Self) -> Ordering {
-        let result = self.hand_type.cmp(&other.hand_type);
-        if result == Ordering::Equal {
-            for (card_a, card_b) in self.cards.iter().zip(other.cards.iter()) {
-                if *card_a == Card::J {
-                    if *card_b != Card::J {
-                        return Ordering::Less;
-                    }
-                } else if *card_b == Card::J {
-                    return Ordering::Greater;
-                }
-                let result = card_a.cmp(card_b);
-                if result != Ordering::Equal {
-                    return result;
-                }
-            }
-        }
-        result
-    }
-}
-
-fn part1(input: &Path) -> usize {
+fn total_winnings<R: Rules + Eq>(input: &Path) -> usize {
     let reader = BufReader::new(File::open(input).unwrap());
-    let mut hands: Vec<Hand> = vec![];
+    let mut hands: Vec<Hand<R>> = vec![];
     for line in reader.lines() {
         let line = line.unwrap();
         let (cards, bid) = line.trim().split_once(' ').unwrap();
         let cards: Vec<Card> = cards.chars().map(|c| c.try_into().unwrap()).collect();
         let bid = bid.parse().unwrap();
-        let hand = Hand::new(cards, bid);
-        hands.push(hand);
+        hands.push(Hand::new(cards, bid));
     }
     hands.sort_unstable();
 
@@ -285,26 +179,48 @@ fn part1(input: &Path) -> usize {
         .sum()
 }
 
-fn part2(input: &Path) -> usize {
-    let reader = BufReader::new(File::open(input).unwrap());
-    let mut hands: Vec<JokerHand> = vec![];
-    for line in reader.lines() {
-        let line = line.unwrap();
-        let (cards, bid) = line.trim().split_once(' ').unwrap();
-        let cards: Vec<Card> = cards.chars().map(|c| c.try_into().unwrap()).collect();
-        let bid = bid.parse().unwrap();
-        let hand = JokerHand::new(cards, bid);
-        hands.push(hand);
-    }
-    hands.sort_unstable();
+fn part1(input: &Path) -> usize {
+    total_winnings::<Standard>(input)
+}
 
-    hands
-        .iter()
-        .enumerate()
-        .map(|(i, hand)| (i + 1) * hand.bid)
-        .sum()
+fn part2(input: &Path) -> usize {
+    total_winnings::<Jokers>(input)
 }
 
 pub fn main(_input: &Path) -> (usize, usize) {
     (part1(_input), part2(_input))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hand_type<R: Rules>(cards: &str) -> HandType {
+        let cards: Vec<Card> = cards.chars().map(|c| c.try_into().unwrap()).collect();
+        HandType::classify::<R>(&cards)
+    }
+
+    #[test]
+    fn ranks_five_card_hands() {
+        let ranking = [
+            "23456", "A23A4", "23432", "TTT98", "23332", "AA8AA", "AAAAA",
+        ];
+        for pair in ranking.windows(2) {
+            assert!(hand_type::<Standard>(pair[0]) < hand_type::<Standard>(pair[1]));
+        }
+    }
+
+    #[test]
+    fn ranks_six_card_hands() {
+        assert!(hand_type::<Standard>("223344") > hand_type::<Standard>("223345"));
+        assert!(hand_type::<Standard>("222333") > hand_type::<Standard>("222334"));
+        assert!(hand_type::<Jokers>("2233J4") > hand_type::<Standard>("223344"));
+    }
+
+    #[test]
+    fn jokers_join_the_largest_group() {
+        assert_eq!(hand_type::<Jokers>("KTJJT"), HandType(vec![4, 1]));
+        assert_eq!(hand_type::<Jokers>("JJJJJ"), HandType(vec![5]));
+        assert_eq!(hand_type::<Standard>("KTJJT"), HandType(vec![2, 2, 1]));
+    }
+}
@@ -7,7 +7,12 @@ use advent2023::{
 
 fn main() {
     let argv: Vec<String> = args().collect();
-    for arg in &argv[1..] {
+    // e.g. --bag="12 red, 13 green, 14 blue" for day 2
+    let bag: Option<day2::Cubes> = argv
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--bag="))
+        .map(|bag| bag.parse().unwrap());
+    for arg in argv[1..].iter().filter(|arg| !arg.starts_with("--")) {
         let day: u8 = arg.parse().unwrap_or_default();
         match day {
             1 => {
@@ -15,7 +20,11 @@ fn main() {
                 println!("{p1}\t{p2}");
             }
             2 => {
-                let (p1, p2) = day2::main("input/day2.txt".into());
+                let input = "input/day2.txt".into();
+                let (p1, p2) = match &bag {
+                    Some(bag) => day2::main_with_bag(input, bag),
+                    None => day2::main(input),
+                };
                 println!("{p1}\t{p2}");
             }
             3 => {
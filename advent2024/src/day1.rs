@@ -1,6 +1,7 @@
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
+use aoc::counter::Counter;
 use camino::Utf8Path as Path;
 
 fn get_numbers(input: &Path) -> (Vec<usize>, Vec<usize>) {
@@ -30,11 +31,8 @@ fn part1(input: &Path) -> usize {
 
 fn part2(input: &Path) -> usize {
     let (a, b) = get_numbers(input);
-    let mut result = 0;
-    for x in a {
-        result += x * b.iter().fold(0, |acc, z| acc + if *z == x { 1 } else { 0 })
-    }
-    result
+    let occurrences: Counter<usize> = b.into_iter().collect();
+    a.iter().map(|x| x * occurrences.get(x)).sum()
 }
 
 pub fn main(input: &Path) -> (usize, usize) {
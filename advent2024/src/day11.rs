@@ -1,18 +1,13 @@
-use std::collections::HashMap;
-
+use aoc::{digits::split_digits_half, memo::Memo};
 use camino::Utf8Path as Path;
 
 fn transform(stone: usize) -> (usize, Option<usize>) {
     if stone == 0 {
         (1, None)
+    } else if let Some((left, right)) = split_digits_half(stone) {
+        (left, Some(right))
     } else {
-        let text = stone.to_string();
-        if text.len() % 2 == 0 {
-            let (left, right) = text.split_at(text.len() / 2);
-            (left.parse().unwrap(), Some(right.parse().unwrap()))
-        } else {
-            (stone * 2024, None)
-        }
+        (stone * 2024, None)
     }
 }
 
@@ -25,22 +20,21 @@ fn both_parts(
     // (stone_number, iterations)
     // more frequently on the same iteration level, so that it isn't too bad,
     // because we can memorize partial results
-    memory: &mut HashMap<(usize, usize), usize>,
+    memory: &mut Memo<(usize, usize), usize>,
 ) -> usize {
     if iterations == 0 {
         1
-    } else if let Some(previous) = memory.get(&(stone, iterations)) {
-        *previous
     } else {
-        let result = match transform(stone) {
-            (new_stone, None) => both_parts(new_stone, iterations - 1, memory),
-            (new_stone, Some(extra_stone)) => {
-                both_parts(new_stone, iterations - 1, memory)
-                    + both_parts(extra_stone, iterations - 1, memory)
-            }
-        };
-        memory.insert((stone, iterations), result);
-        result
+        memory.get_or_compute(
+            (stone, iterations),
+            |memory, (stone, iterations)| match transform(stone) {
+                (new_stone, None) => both_parts(new_stone, iterations - 1, memory),
+                (new_stone, Some(extra_stone)) => {
+                    both_parts(new_stone, iterations - 1, memory)
+                        + both_parts(extra_stone, iterations - 1, memory)
+                }
+            },
+        )
     }
 }
 
@@ -53,7 +47,7 @@ pub fn main(input: &Path) -> (usize, usize) {
         .collect();
 
     // (stone_number, remaining_iterations) -> number of stones at 0 iterations remaining
-    let mut memory: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut memory = Memo::new();
 
     (
         values
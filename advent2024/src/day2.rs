@@ -1,10 +1,7 @@
 use std::{
-    cmp::{
-        max, min,
-        Ordering::{self},
-    },
     fs::File,
     io::{BufRead, BufReader},
+    ops::RangeInclusive,
 };
 
 use camino::Utf8Path as Path;
@@ -23,53 +20,88 @@ fn read_input(input: &Path) -> Vec<Vec<usize>> {
     result
 }
 
-fn determine_ordering(levels: &[usize], use_dampener: bool) -> Ordering {
-    if use_dampener {
-        let left = min(levels[0], levels[1]);
-        let right_values = levels.last_chunk::<2>().unwrap();
-        let right = max(right_values[0], right_values[1]);
-        left.cmp(&right)
-    } else {
-        levels[0].cmp(&levels[1])
-    }
+/// A report is safe if it is strictly ascending or descending in steps that lie within `steps`,
+/// possibly after leaving out up to `max_removals` levels.
+#[derive(Debug, Clone)]
+struct Checker {
+    steps: RangeInclusive<usize>,
+    max_removals: usize,
 }
 
-fn is_safe(levels: &[usize], use_dampener: bool) -> bool {
-    let ordering = determine_ordering(levels, use_dampener);
-    if ordering == Ordering::Equal {
-        return false; // neither ascending nor descending
+impl Checker {
+    fn new(steps: RangeInclusive<usize>, max_removals: usize) -> Self {
+        Self {
+            steps,
+            max_removals,
+        }
+    }
+
+    fn is_safe(&self, levels: &[usize]) -> bool {
+        self.removals(levels).is_some()
+    }
+
+    /// the fewest indices that have to be removed to make the report safe
+    fn removals(&self, levels: &[usize]) -> Option<Vec<usize>> {
+        let ascending = self.removals_in_direction(levels, true);
+        let descending = self.removals_in_direction(levels, false);
+        match (ascending, descending) {
+            (Some(a), Some(d)) => Some(if d.len() < a.len() { d } else { a }),
+            (a, d) => a.or(d),
+        }
     }
-    if levels
-        .iter()
-        .zip(&levels[1..])
-        .all(|(a, b)| a.cmp(b) == ordering && a.abs_diff(*b) <= 3)
-    {
-        true // all flawless!
-    } else if use_dampener {
-        let mut sublist: Vec<usize> = levels[1..].into();
-
-        // brute force: Try leaving each level out
-        for (i, level) in levels.iter().enumerate() {
-            if is_safe(&sublist, false) {
-                return true;
+
+    /// Dynamic programming over the last kept level. Since at most `max_removals` levels can be
+    /// skipped, every level only has to look back that far, which makes this O(n * k).
+    fn removals_in_direction(&self, levels: &[usize], ascending: bool) -> Option<Vec<usize>> {
+        let k = self.max_removals;
+        let n = levels.len();
+        if n == 0 {
+            return Some(vec![]);
+        }
+        let is_step = |a: usize, b: usize| {
+            (if ascending { a < b } else { a > b }) && self.steps.contains(&a.abs_diff(b))
+        };
+        // fewest removals so that the kept levels end with level i, and the level kept before it
+        let mut removals: Vec<Option<usize>> = vec![None; n];
+        let mut previous: Vec<Option<usize>> = vec![None; n];
+        for i in 0..n {
+            if i <= k {
+                removals[i] = Some(i); // drop everything in front
             }
+            for j in i.saturating_sub(k + 1)..i {
+                let Some(before) = removals[j] else {
+                    continue;
+                };
+                let total = before + (i - j - 1);
+                if is_step(levels[j], levels[i]) && removals[i].is_none_or(|r| total < r) {
+                    removals[i] = Some(total);
+                    previous[i] = Some(j);
+                }
+            }
+        }
+        let (last, total) = (n.saturating_sub(k + 1)..n)
+            .filter_map(|i| removals[i].map(|r| (i, r + (n - 1 - i))))
+            .min_by_key(|(_, total)| *total)?;
+        if total > k {
+            return None;
+        }
 
-            if i < sublist.len() {
-                // swap next level out
-                sublist[i] = *level;
-            } // else: last loop iteration
+        let mut kept = vec![false; n];
+        let mut current = Some(last);
+        while let Some(i) = current {
+            kept[i] = true;
+            current = previous[i];
         }
-        false // found no safe subset
-    } else {
-        false
+        Some((0..n).filter(|i| !kept[*i]).collect())
     }
 }
 
 fn part1(input: &Path) -> usize {
     let records = read_input(input);
+    let checker = Checker::new(1..=3, 0);
     let mut result = 0;
     for record in records {
-        if is_safe(&record, false) {
+        if checker.is_safe(&record) {
             result += 1;
         }
     }
@@ -78,9 +110,10 @@ fn part1(input: &Path) -> usize {
 
 fn part2(input: &Path) -> usize {
     let records = read_input(input);
+    let checker = Checker::new(1..=3, 1);
     let mut result = 0;
     for record in &records {
-        if is_safe(record, true) {
+        if checker.is_safe(record) {
             result += 1;
         }
     }
@@ -90,3 +123,82 @@ fn part2(input: &Path) -> usize {
 pub fn main(input: &Path) -> (usize, usize) {
     (part1(input), part2(input))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the fewest removals by trying every subset of kept levels
+    fn brute_force(checker: &Checker, levels: &[usize]) -> Option<usize> {
+        let n = levels.len();
+        (0..1usize << n)
+            .filter(|kept| {
+                let kept: Vec<usize> = (0..n)
+                    .filter(|i| kept & (1 << i) != 0)
+                    .map(|i| levels[i])
+                    .collect();
+                let steps = |ascending: bool| {
+                    kept.windows(2).all(|w| {
+                        (if ascending { w[0] < w[1] } else { w[0] > w[1] })
+                            && checker.steps.contains(&w[0].abs_diff(w[1]))
+                    })
+                };
+                steps(true) || steps(false)
+            })
+            .map(|kept| n - kept.count_ones() as usize)
+            .filter(|removed| *removed <= checker.max_removals)
+            .min()
+    }
+
+    #[test]
+    fn samples() {
+        let reports = [
+            [7, 6, 4, 2, 1],
+            [1, 2, 7, 8, 9],
+            [9, 7, 6, 2, 1],
+            [1, 3, 2, 4, 5],
+            [8, 6, 4, 4, 1],
+            [1, 3, 6, 7, 9],
+        ];
+        let strict = Checker::new(1..=3, 0);
+        let dampened = Checker::new(1..=3, 1);
+        let safe = |checker: &Checker| reports.iter().filter(|r| checker.is_safe(*r)).count();
+        assert_eq!(safe(&strict), 2);
+        assert_eq!(safe(&dampened), 4);
+        assert_eq!(dampened.removals(&reports[3]).map(|r| r.len()), Some(1));
+    }
+
+    #[test]
+    fn short_reports_remove_as_few_levels_as_possible() {
+        let checker = Checker::new(1..=3, 2);
+        assert_eq!(checker.removals(&[9, 1, 11]).map(|r| r.len()), Some(1));
+        assert_eq!(checker.removals(&[5, 5]).map(|r| r.len()), Some(1));
+        assert_eq!(checker.removals(&[5]), Some(vec![]));
+        assert_eq!(checker.removals(&[]), Some(vec![]));
+    }
+
+    #[test]
+    fn agrees_with_brute_force() {
+        for k in 0..3 {
+            let checker = Checker::new(1..=3, k);
+            for n in 0..=6 {
+                for code in 0..5usize.pow(n) {
+                    let levels: Vec<usize> = (0..n).map(|i| code / 5usize.pow(i) % 5).collect();
+                    let removals = checker.removals(&levels);
+                    assert_eq!(
+                        removals.as_ref().map(Vec::len),
+                        brute_force(&checker, &levels),
+                        "{levels:?} with k={k}"
+                    );
+                    if let Some(removals) = removals {
+                        let kept: Vec<usize> = (0..levels.len())
+                            .filter(|i| !removals.contains(i))
+                            .map(|i| levels[i])
+                            .collect();
+                        assert!(Checker::new(1..=3, 0).is_safe(&kept), "{levels:?}");
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,52 +1,289 @@
+use std::{fmt::Write, ops::Range};
+
 use camino::Utf8Path as Path;
 
-/// parse factors and multiply them if the instruction is valid
-fn parse_factors(mul_str: &str) -> Option<usize> {
-    let (left, right) = mul_str.split_once(",")?;
-    let a: usize = left.parse().ok()?;
-    let left = right.split_once(")")?.0;
-    let b: usize = left.parse().ok()?;
-    Some(a * b)
+pub type Value = i64;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Kind {
+    /// like `do()`
+    Enable,
+    /// like `don't()`
+    Disable,
+    /// contributes to the total while enabled
+    Compute(fn(&[Value]) -> Value),
 }
 
-/// gather and execute all active multiplication instructions
-fn gather_muls(parts: &[&str], out: &mut Vec<usize>) {
-    for part in parts {
-        if let Some(factors) = parse_factors(part) {
-            out.push(factors)
-        }
+/// Recognized as `name(a,b,...)` with exactly `arity` operands of 1 to 3 digits each
+#[derive(Debug, Clone)]
+pub struct Instruction {
+    pub name: &'static str,
+    pub arity: usize,
+    pub kind: Kind,
+}
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    /// index into the registered instructions
+    pub instruction: usize,
+    pub operands: Vec<Value>,
+    /// where the instruction was found in the code
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub token: Token,
+    /// `None` for `Enable` and `Disable`, or if the instruction was skipped
+    pub value: Option<Value>,
+    /// the instruction would have computed a value but was disabled
+    pub skipped: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Execution {
+    pub total: Value,
+    pub trace: Vec<Step>,
+}
+
+impl Execution {
+    pub fn skipped(&self) -> impl Iterator<Item = &Step> {
+        self.trace.iter().filter(|step| step.skipped)
     }
 }
 
-fn read_code(input: &Path, use_conditionals: bool) -> Vec<usize> {
-    let mut result = vec![];
-    let data = std::fs::read_to_string(input).unwrap();
-    if use_conditionals {
-        let donts: Vec<&str> = data.split("don't()").collect();
-        let parts: Vec<&str> = donts[0].split("mul(").collect(); // remember: implicit do() at the beginning!
-        gather_muls(&parts, &mut result);
+#[derive(Debug, Clone, Default)]
+pub struct Interpreter {
+    instructions: Vec<Instruction>,
+}
 
-        for dont in &donts[1..] {
-            if let Some((_no, yes)) = dont.split_once("do()") {
-                let parts: Vec<&str> = yes.split("mul(").collect();
-                gather_muls(&parts[1..], &mut result);
+impl Interpreter {
+    /// `mul`, `do` and `don't` from the puzzle
+    pub fn standard() -> Self {
+        let mut result = Self::default();
+        result
+            .register(
+                "mul",
+                2,
+                Kind::Compute(|operands| operands[0] * operands[1]),
+            )
+            .register("do", 0, Kind::Enable)
+            .register("don't", 0, Kind::Disable);
+        result
+    }
+
+    pub fn register(&mut self, name: &'static str, arity: usize, kind: Kind) -> &mut Self {
+        self.instructions.push(Instruction { name, arity, kind });
+        self
+    }
+
+    /// find all valid instructions in a single scan, everything else is garbage
+    pub fn tokenize(&self, code: &str) -> Vec<Token> {
+        let bytes = code.as_bytes();
+        let mut result = vec![];
+        let mut pos = 0;
+        while pos < bytes.len() {
+            let token = self
+                .instructions
+                .iter()
+                .enumerate()
+                .find_map(|(i, instruction)| match_instruction(bytes, pos, i, instruction));
+            match token {
+                Some(token) => {
+                    pos = token.span.end;
+                    result.push(token);
+                }
+                None => pos += 1,
             }
         }
-    } else {
-        let parts: Vec<&str> = data.split("mul(").collect();
-        gather_muls(&parts[1..], &mut result);
+        result
+    }
+
+    /// Without conditionals, `Enable` and `Disable` instructions have no effect
+    pub fn run(&self, tokens: &[Token], use_conditionals: bool) -> Execution {
+        let mut result = Execution::default();
+        let mut enabled = true;
+        for token in tokens {
+            let kind = self.instructions[token.instruction].kind;
+            let value = match kind {
+                Kind::Enable => {
+                    enabled |= use_conditionals;
+                    None
+                }
+                Kind::Disable => {
+                    enabled &= !use_conditionals;
+                    None
+                }
+                Kind::Compute(f) if enabled => Some(f(&token.operands)),
+                Kind::Compute(_) => None,
+            };
+            result.total += value.unwrap_or(0);
+            result.trace.push(Step {
+                token: token.clone(),
+                value,
+                skipped: !enabled && matches!(kind, Kind::Compute(_)),
+            });
+        }
+        result
+    }
+
+    /// one line per instruction with its position in the code and its value
+    pub fn dump_trace(&self, execution: &Execution) -> String {
+        let mut result = String::new();
+        for step in &execution.trace {
+            let token = &step.token;
+            let instruction = &self.instructions[token.instruction];
+            let operands: Vec<String> = token.operands.iter().map(Value::to_string).collect();
+            let outcome = match step.value {
+                Some(value) => format!("= {value}"),
+                None if step.skipped => String::from("skipped"),
+                None => String::new(),
+            };
+            writeln!(
+                result,
+                "{:>6}..{:<6} {}({}) {}",
+                token.span.start,
+                token.span.end,
+                instruction.name,
+                operands.join(","),
+                outcome
+            )
+            .unwrap();
+        }
+        result
+    }
+}
+
+fn match_instruction(
+    bytes: &[u8],
+    start: usize,
+    index: usize,
+    instruction: &Instruction,
+) -> Option<Token> {
+    let mut pos = start;
+    let expect = |literal: &[u8], pos: &mut usize| {
+        let found = bytes[*pos..].starts_with(literal);
+        if found {
+            *pos += literal.len();
+        }
+        found
+    };
+    if !expect(instruction.name.as_bytes(), &mut pos) || !expect(b"(", &mut pos) {
+        return None;
+    }
+    let mut operands = Vec::with_capacity(instruction.arity);
+    for i in 0..instruction.arity {
+        if i > 0 && !expect(b",", &mut pos) {
+            return None;
+        }
+        let digits = bytes[pos..]
+            .iter()
+            .take(4)
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if !(1..=3).contains(&digits) {
+            return None;
+        }
+        let operand = bytes[pos..pos + digits]
+            .iter()
+            .fold(0, |n, b| n * 10 + Value::from(b - b'0'));
+        operands.push(operand);
+        pos += digits;
     }
-    result
+    if !expect(b")", &mut pos) {
+        return None;
+    }
+    Some(Token {
+        instruction: index,
+        operands,
+        span: start..pos,
+    })
+}
+
+fn run(code: &str, use_conditionals: bool) -> usize {
+    let interpreter = Interpreter::standard();
+    let tokens = interpreter.tokenize(code);
+    interpreter.run(&tokens, use_conditionals).total as usize
 }
 
-fn part1(input: &Path) -> usize {
-    read_code(input, false).iter().sum()
+fn part1(code: &str) -> usize {
+    run(code, false)
 }
 
-fn part2(input: &Path) -> usize {
-    read_code(input, true).iter().sum()
+fn part2(code: &str) -> usize {
+    run(code, true)
 }
 
 pub fn main(input: &Path) -> (usize, usize) {
-    (part1(input), part2(input))
+    let code = std::fs::read_to_string(input).unwrap();
+    (part1(&code), part2(&code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn operands(tokens: &[Token]) -> Vec<Vec<Value>> {
+        tokens.iter().map(|token| token.operands.clone()).collect()
+    }
+
+    #[test]
+    fn samples() {
+        let sample1 = "xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))";
+        assert_eq!(part1(sample1), 161);
+        let sample2 = "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+        assert_eq!(part1(sample2), 161);
+        assert_eq!(part2(sample2), 48);
+    }
+
+    #[test]
+    fn operands_and_spans() {
+        let interpreter = Interpreter::standard();
+        let tokens =
+            interpreter.tokenize("mul(1234,5)mul(123,45)mul(,1)mul(1,2,3)mul(007,1)mul(4,5");
+        assert_eq!(operands(&tokens), [vec![123, 45], vec![7, 1]]);
+        let spans: Vec<Range<usize>> = tokens.iter().map(|token| token.span.clone()).collect();
+        assert_eq!(spans, [11..22, 39..49]);
+        assert!(interpreter.tokenize("mul ( 1,2)").is_empty());
+    }
+
+    #[test]
+    fn custom_instructions() {
+        let mut interpreter = Interpreter::standard();
+        interpreter.register("add", 3, Kind::Compute(|operands| operands.iter().sum()));
+        let tokens =
+            interpreter.tokenize("add(1,2,3)don't()add(4,5,6)mul(2,3)do()mul(1,1)add(1,2)");
+        assert_eq!(tokens.len(), 6);
+        assert_eq!(tokens[0].instruction, 3);
+
+        let execution = interpreter.run(&tokens, true);
+        assert_eq!(execution.total, 6 + 1);
+        let skipped: Vec<bool> = execution.trace.iter().map(|step| step.skipped).collect();
+        assert_eq!(skipped, [false, false, true, true, false, false]);
+        let values: Vec<Option<Value>> = execution.trace.iter().map(|step| step.value).collect();
+        assert_eq!(values, [Some(6), None, None, None, None, Some(1)]);
+        assert_eq!(execution.skipped().count(), 2);
+
+        let execution = interpreter.run(&tokens, false);
+        assert_eq!(execution.total, 6 + 15 + 6 + 1);
+        assert_eq!(execution.skipped().count(), 0);
+    }
+
+    #[test]
+    fn trace() {
+        let interpreter = Interpreter::standard();
+        let tokens = interpreter.tokenize("mul(2,3)don't()mul(4,5)do()");
+        let execution = interpreter.run(&tokens, true);
+        let dump = interpreter.dump_trace(&execution);
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "     0..8      mul(2,3) = 6",
+                "     8..15     don't() ",
+                "    15..23     mul(4,5) skipped",
+                "    23..27     do() ",
+            ]
+        );
+    }
 }
@@ -1,119 +1,300 @@
-use std::{fs::read_to_string, str::Chars};
+use std::fs::read_to_string;
 
 use camino::Utf8Path as Path;
 
-fn count(s: &str, pattern: &'static str) -> usize {
-    s.matches(pattern).count()
+/// `(row, col)` steps: right, down-right, down, down-left, left, up-left, up, up-right
+pub const DIRECTIONS: [(isize, isize); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+
+/// A rectangular grid of characters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid {
+    cells: Vec<Vec<char>>,
+    cols: usize,
 }
 
-fn transform_vertical(old_lines: &[&str]) -> Vec<String> {
-    let n = old_lines[0].len(); // assuming equally sized strings
-    let mut iters: Vec<Chars> = old_lines.iter().map(|s| s.chars()).collect();
-    let mut result = Vec::with_capacity(n);
+impl std::str::FromStr for Grid {
+    type Err = String;
 
-    for _ in 0..n {
-        let mut new_line = String::with_capacity(n);
-        for iter in &mut iters {
-            let c: char = iter.next().unwrap();
-            new_line.push(c);
+    fn from_str(s: &str) -> Result<Self, String> {
+        let cells: Vec<Vec<char>> = s
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.trim_end().chars().collect())
+            .collect();
+        let cols = cells.first().map_or(0, Vec::len);
+        if let Some(row) = cells.iter().position(|row| row.len() != cols) {
+            return Err(format!(
+                "line {} has {} columns instead of {cols}",
+                row + 1,
+                cells[row].len()
+            ));
         }
-        result.push(new_line);
+        Ok(Self { cells, cols })
     }
-    result
 }
 
-// shift and padd the original strings so that it's easy to process them afterwards
-fn transform_diagonal(old_lines: &[&str], ascending_diagonal: bool) -> Vec<String> {
-    let n = old_lines.len();
-    let mut shifted: Vec<String> = Vec::with_capacity(n);
-    if ascending_diagonal {
-        for (i, s) in old_lines.iter().enumerate() {
-            let mut new_string = " ".repeat(n - i);
-            new_string.push_str(s);
-            let right_padding = " ".repeat(i);
-            new_string.push_str(&right_padding);
-            shifted.push(new_string);
-        }
-    } else {
-        //then: descending diagonal
-        for (i, s) in old_lines.iter().enumerate() {
-            let mut new_string = " ".repeat(i);
-            new_string.push_str(s);
-            let right_padding = " ".repeat(n - i);
-            new_string.push_str(&right_padding);
-            shifted.push(new_string);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Occurrence {
+    /// index into the searched words
+    pub word: usize,
+    /// position of the first character
+    pub row: usize,
+    pub col: usize,
+    /// one of `DIRECTIONS`
+    pub direction: (isize, isize),
+}
+
+impl Grid {
+    pub fn rows(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn get(&self, row: isize, col: isize) -> Option<char> {
+        let row = usize::try_from(row).ok()?;
+        let col = usize::try_from(col).ok()?;
+        self.cells.get(row)?.get(col).copied()
+    }
+
+    fn matches_word(
+        &self,
+        word: &[char],
+        row: usize,
+        col: usize,
+        (dr, dc): (isize, isize),
+    ) -> bool {
+        word.iter().enumerate().all(|(i, c)| {
+            let i = i as isize;
+            self.get(row as isize + i * dr, col as isize + i * dc) == Some(*c)
+        })
+    }
+
+    /// every occurrence of every word in all 8 directions. Palindromes are found twice for each
+    /// position, once per direction. Empty words are never found.
+    pub fn find_words(&self, words: &[&str]) -> Vec<Occurrence> {
+        let words: Vec<Vec<char>> = words.iter().map(|word| word.chars().collect()).collect();
+        let mut result = vec![];
+        for row in 0..self.rows() {
+            for col in 0..self.cols {
+                for (index, word) in words.iter().enumerate() {
+                    if word.first() != Some(&self.cells[row][col]) {
+                        continue;
+                    }
+                    for direction in DIRECTIONS {
+                        // a single character would otherwise be found in every direction
+                        if word.len() == 1 && direction != DIRECTIONS[0] {
+                            break;
+                        }
+                        if self.matches_word(word, row, col, direction) {
+                            result.push(Occurrence {
+                                word: index,
+                                row,
+                                col,
+                                direction,
+                            });
+                        }
+                    }
+                }
+            }
         }
+        result
     }
 
-    let slices: Vec<&str> = shifted.iter().map(|s| s.as_str()).collect();
-    transform_vertical(&slices)
+    /// top left corners of all places where the stencil fits
+    pub fn find_stencil(&self, stencil: &Stencil) -> Vec<(usize, usize)> {
+        let mut result = vec![];
+        if stencil.rows() > self.rows() || stencil.cols() > self.cols {
+            return result;
+        }
+        for row in 0..=self.rows() - stencil.rows() {
+            for col in 0..=self.cols - stencil.cols() {
+                if stencil.matches_at(self, row, col) {
+                    result.push((row, col));
+                }
+            }
+        }
+        result
+    }
 }
 
-fn part1(input: &Path) -> usize {
-    let text = read_to_string(input).unwrap();
-    let mut result = 0;
-    let lines: Vec<&str> = text.lines().collect();
+/// A 2D pattern in which `None` matches any character
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stencil {
+    cells: Vec<Vec<Option<char>>>,
+}
 
-    // lines is horizontal
-    for line in &lines {
-        result += count(line, "XMAS");
-        result += count(line, "SAMX");
-    }
-    let vertical = transform_vertical(&lines);
-    for line in vertical {
-        result += count(&line, "XMAS");
-        result += count(&line, "SAMX");
+impl Stencil {
+    /// One line per row, `wildcard` matches any character. All rows must be equally long.
+    /// Unlike a `Grid`, lines are taken as they are, so that spaces can be wildcards.
+    pub fn new(pattern: &str, wildcard: char) -> Result<Self, String> {
+        let cells: Vec<Vec<Option<char>>> = pattern
+            .lines()
+            .map(|line| {
+                line.chars()
+                    .map(|c| if c == wildcard { None } else { Some(c) })
+                    .collect()
+            })
+            .collect();
+        let cols = cells.first().map_or(0, Vec::len);
+        if let Some(row) = cells.iter().position(|row| row.len() != cols) {
+            return Err(format!(
+                "line {} has {} columns instead of {cols}",
+                row + 1,
+                cells[row].len()
+            ));
+        }
+        Ok(Self { cells })
     }
-    let diagonal = transform_diagonal(&lines, true);
-    for line in diagonal {
-        result += count(&line, "XMAS");
-        result += count(&line, "SAMX");
+
+    pub fn rows(&self) -> usize {
+        self.cells.len()
     }
 
-    let diagonal = transform_diagonal(&lines, false);
-    for line in diagonal {
-        result += count(&line, "XMAS");
-        result += count(&line, "SAMX");
+    pub fn cols(&self) -> usize {
+        self.cells.first().map_or(0, Vec::len)
     }
-    result
-}
 
-fn part2(input: &Path) -> usize {
-    let text = read_to_string(input).unwrap();
-    let n = text.find("\n").unwrap(); // assuming square shape
+    fn matches_at(&self, grid: &Grid, row: usize, col: usize) -> bool {
+        self.cells.iter().enumerate().all(|(r, line)| {
+            line.iter().enumerate().all(|(c, expected)| match expected {
+                Some(expected) => grid.cells[row + r][col + c] == *expected,
+                None => true,
+            })
+        })
+    }
 
-    let mut grid: Vec<Vec<char>> = Vec::with_capacity(n);
-    for line in text.lines() {
-        let v = line.chars().collect();
-        grid.push(v);
+    /// clockwise by 90 degrees
+    pub fn rotated(&self) -> Self {
+        let rows = self.rows();
+        let cells = (0..self.cols())
+            .map(|c| (0..rows).rev().map(|r| self.cells[r][c]).collect())
+            .collect();
+        Self { cells }
     }
 
-    let mut result = 0;
-    for (row, line) in grid.iter().enumerate().skip(1) {
-        if row + 1 == n {
-            continue; // grid boundary
-        }
-        for (col, c) in line.iter().enumerate().skip(1) {
-            if col + 1 == n {
-                continue; // grid boundary
-            }
-            if *c == 'A' {
-                // top left, top right, bottom left, bottom right
-                let tl = grid[row + 1][col - 1];
-                let tr = grid[row + 1][col + 1];
-                let bl = grid[row - 1][col - 1];
-                let br = grid[row - 1][col + 1];
-                let has_descending = tl == 'M' && br == 'S' || tl == 'S' && br == 'M';
-                let has_ascending = bl == 'M' && tr == 'S' || bl == 'S' && tr == 'M';
-                if has_ascending && has_descending {
-                    result += 1;
-                }
+    /// the stencil in all 4 orientations without duplicates for symmetric stencils
+    pub fn rotations(&self) -> Vec<Self> {
+        let mut result: Vec<Self> = vec![];
+        let mut current = self.clone();
+        for _ in 0..4 {
+            let next = current.rotated();
+            if !result.contains(&current) {
+                result.push(current);
             }
+            current = next;
         }
+        result
     }
-    result
+}
+
+fn read_grid(input: &Path) -> Grid {
+    read_to_string(input)
+        .unwrap()
+        .parse()
+        .unwrap_or_else(|err| panic!("{err}"))
+}
+
+fn part1(grid: &Grid) -> usize {
+    grid.find_words(&["XMAS"]).len()
+}
+
+fn part2(grid: &Grid) -> usize {
+    let cross = Stencil::new("M.S\n.A.\nM.S", '.').unwrap();
+    cross
+        .rotations()
+        .iter()
+        .map(|stencil| grid.find_stencil(stencil).len())
+        .sum()
 }
 
 pub fn main(input: &Path) -> (usize, usize) {
-    (part1(input), part2(input))
+    let grid = read_grid(input);
+    (part1(&grid), part2(&grid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "MMMSXXMASM
+MSAMXMSMSA
+AMXSXMAAMM
+MSAMASMSMX
+XMASAMXAMM
+XXAMMXXAMA
+SMSMSASXSS
+SAXAMASAAA
+MAMMMXMMMM
+MXMXAXMASX
+";
+
+    #[test]
+    fn sample() {
+        let grid: Grid = SAMPLE.parse().unwrap();
+        assert_eq!(part1(&grid), 18);
+        assert_eq!(part2(&grid), 9);
+    }
+
+    #[test]
+    fn words_with_positions_and_directions() {
+        let grid: Grid = "ABC\nDEF".parse().unwrap();
+        assert_eq!((grid.rows(), grid.cols()), (2, 3));
+        let found = grid.find_words(&["CB", "AE", "X"]);
+        assert_eq!(
+            found,
+            [
+                Occurrence {
+                    word: 1,
+                    row: 0,
+                    col: 0,
+                    direction: (1, 1)
+                },
+                Occurrence {
+                    word: 0,
+                    row: 0,
+                    col: 2,
+                    direction: (0, -1)
+                },
+            ]
+        );
+        assert_eq!(grid.find_words(&["F"]).len(), 1);
+        assert_eq!(grid.find_words(&[""]).len(), 0);
+    }
+
+    #[test]
+    fn rejects_ragged_grids() {
+        assert!("AB\nC".parse::<Grid>().is_err());
+    }
+
+    #[test]
+    fn stencils_on_rectangular_grids() {
+        let grid: Grid = "ABAB\nBABA".parse().unwrap();
+        let stencil = Stencil::new("A.\n.A", '.').unwrap();
+        assert_eq!(grid.find_stencil(&stencil), [(0, 0), (0, 2)]);
+        assert_eq!(stencil.rotations().len(), 2);
+        let tall = Stencil::new("A\nB\nA", '.').unwrap();
+        assert!(grid.find_stencil(&tall).is_empty());
+        assert_eq!(grid.find_stencil(&tall.rotated()), [(0, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn spaces_as_wildcards() {
+        let stencil = Stencil::new("A  \n   \n  B", ' ').unwrap();
+        assert_eq!((stencil.rows(), stencil.cols()), (3, 3));
+        let grid: Grid = "AXY\nXXX\nYXB\nZZZ".parse().unwrap();
+        assert_eq!(grid.find_stencil(&stencil), [(0, 0)]);
+        assert!(Stencil::new("A \nB", ' ').is_err());
+    }
 }
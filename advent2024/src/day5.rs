@@ -1,73 +1,70 @@
-use std::{
-    collections::HashSet,
-    fs::File,
-    io::{BufRead, BufReader},
-};
+use std::{num::ParseIntError, str::FromStr};
 
+use aoc::paragraphs::{self, FromBlock};
 use camino::Utf8Path as Path;
 
-fn read_data(input: &Path) -> (HashSet<(usize, usize)>, Vec<Vec<usize>>) {
-    let mut forbidden_orderings = HashSet::new();
-    let mut all_pages = vec![];
-    let reader = BufReader::new(File::open(input).unwrap());
-    let mut read_ordering = true;
-    for line in reader.lines() {
-        let line = line.unwrap();
-        if read_ordering {
-            if let Some((a, b)) = line.split_once("|") {
-                forbidden_orderings.insert((b.parse().unwrap(), a.parse().unwrap()));
-            } else {
-                assert_eq!(line, "");
-                read_ordering = false;
-            }
-        } else {
-            let pages: Vec<usize> = line
-                .split(',')
-                .map(|page| page.parse::<usize>().unwrap())
-                .collect();
-            all_pages.push(pages);
+use crate::ordering::Precedence;
+
+/// lines like `47|53`
+struct Rules(Precedence<usize>);
+
+impl FromBlock for Rules {
+    type Err = String;
+
+    fn from_block(lines: &[String]) -> Result<Self, String> {
+        let mut rules = Precedence::new();
+        for line in lines {
+            let (a, b) = line
+                .split_once('|')
+                .ok_or_else(|| format!("not a rule: {line}"))?;
+            let page = |p: &str| p.parse().map_err(|_| format!("bad page: {p}"));
+            rules.add_rule(page(a)?, page(b)?);
         }
+        Ok(Rules(rules))
     }
-    (forbidden_orderings, all_pages)
 }
 
-fn part1(input: &Path) -> usize {
-    let mut result = 0;
-    let (forbidden, all_pages) = read_data(input);
-    for pageset in all_pages {
-        let mut found_error = false;
-        for pair in pageset.windows(2) {
-            let pair = (pair[0], pair[1]);
-            if forbidden.contains(&pair) {
-                found_error = true;
-                break;
-            }
-        }
-        if !found_error {
-            result += pageset[pageset.len() / 2];
-        }
+/// lines like `75,47,61,53,29`
+struct Update(Vec<usize>);
+
+impl FromStr for Update {
+    type Err = ParseIntError;
+
+    fn from_str(line: &str) -> Result<Self, ParseIntError> {
+        line.split(',')
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(Update)
     }
-    result
+}
+
+fn read_data(input: &Path) -> (Precedence<usize>, Vec<Vec<usize>>) {
+    let (Rules(rules), updates): (Rules, Vec<Update>) = paragraphs::from_file(input)
+        .sections2()
+        .unwrap_or_else(|err| panic!("{err}"));
+    (
+        rules,
+        updates.into_iter().map(|Update(pages)| pages).collect(),
+    )
+}
+
+fn part1(input: &Path) -> usize {
+    let (rules, all_pages) = read_data(input);
+    all_pages
+        .iter()
+        .filter(|pageset| rules.is_sorted(pageset))
+        .map(|pageset| pageset[pageset.len() / 2])
+        .sum()
 }
 
 fn part2(input: &Path) -> usize {
-    let (forbidden, mut all_pages) = read_data(input);
+    let (rules, mut all_pages) = read_data(input);
     let mut result = 0;
     for pageset in &mut all_pages {
-        let mut faulty_pageset = false;
-        let mut seen_error = true; // initial value to get loop started
-        while seen_error {
-            seen_error = false;
-            for (i, win) in pageset.windows(2).enumerate() {
-                if forbidden.contains(&(win[0], win[1])) {
-                    pageset.swap(i, i + 1);
-                    seen_error = true;
-                    faulty_pageset = true;
-                    break; // start from beginning, tolerating quadratic runtime
-                }
-            }
-        }
-        if faulty_pageset {
+        if !rules.is_sorted(pageset) {
+            rules
+                .sort(pageset)
+                .unwrap_or_else(|err| panic!("contradicting rules: {err}"));
             result += pageset[pageset.len() / 2];
         }
     }
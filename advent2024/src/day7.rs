@@ -4,6 +4,7 @@ use std::{
     io::{BufRead, BufReader},
 };
 
+use aoc::digits::strip_suffix_digits;
 use camino::Utf8Path as Path;
 
 struct Equation {
@@ -30,10 +31,8 @@ fn read_equations(input: &Path) -> Vec<Equation> {
 }
 
 fn unglue(current: usize, last: usize, operands: &[usize]) -> bool {
-    let current_str = current.to_string();
-    let last_str = last.to_string();
-    if let Some(new_str) = current_str.strip_suffix(&last_str) {
-        solve_recursively(new_str.parse().unwrap(), operands, true)
+    if let Some(rest) = strip_suffix_digits(current, last) {
+        solve_recursively(rest, operands, true)
     } else {
         false // can't be unglued
     }
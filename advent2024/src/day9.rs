@@ -1,116 +1,244 @@
-use camino::Utf8Path as Path;
+use std::{cmp::Reverse, collections::BinaryHeap, fmt};
 
-const BLANK: usize = usize::MAX;
+use camino::Utf8Path as Path;
 
 #[derive(Debug, Clone)]
 struct File {
-    id: usize,
     len: u8,
     trailing_space: u8,
 }
 
 fn read_disk(input: &Path) -> Vec<File> {
-    let mut result = vec![];
-    let disk = std::fs::read_to_string(input).unwrap();
-    let iter_file = disk.chars().step_by(2);
-    let iter_space = disk.chars().skip(1).step_by(2);
-    for (id, (file_len, space_len)) in iter_file.zip(iter_space).enumerate() {
-        let len = file_len.to_digit(10).unwrap() as u8;
-        let trailing_space = space_len.to_digit(10).unwrap_or(0) as u8;
-        result.push(File {
-            id,
-            len,
-            trailing_space,
-        });
-    }
-    result
+    parse_layout(&std::fs::read_to_string(input).unwrap())
 }
 
-fn part1(files: &[File]) -> usize {
-    let mut blocks: Vec<usize> = Vec::with_capacity(files.iter().map(|f| f.len as usize).sum());
-    let mut right_file_iter = files.iter().rev();
-    let mut right_file = right_file_iter.next().cloned().unwrap();
-    'outer: for left_file in files {
-        if left_file.id == right_file.id {
-            let already_inserted = blocks.iter().filter(|b| **b == left_file.id).count();
-            blocks.extend_from_slice(&vec![
-                left_file.id;
-                (left_file.len as usize) - already_inserted
-            ]);
-
-            break;
-        }
-        blocks.extend_from_slice(&vec![left_file.id; left_file.len as usize]);
+fn parse_layout(disk: &str) -> Vec<File> {
+    let digits: Vec<u8> = disk
+        .trim()
+        .chars()
+        .map(|c| c.to_digit(10).unwrap() as u8)
+        .collect();
+    // the last file has no trailing space
+    digits
+        .chunks(2)
+        .map(|chunk| File {
+            len: chunk[0],
+            trailing_space: chunk.get(1).cloned().unwrap_or(0),
+        })
+        .collect()
+}
 
-        let mut space = left_file.trailing_space;
-        while let Some(diff) = space.checked_sub(right_file.len) {
-            space = diff;
-            blocks.extend_from_slice(&vec![right_file.id; right_file.len as usize]);
+#[derive(Debug, Clone, Copy)]
+struct Span {
+    start: usize,
+    len: u8,
+}
+
+/// The disk as a list of spans instead of individual blocks.
+///
+/// Free spans are bucketed by their length (a gap is never longer than 9 blocks) and each bucket
+/// is a min-heap over the start positions, so the leftmost gap of at least n blocks is found by
+/// peeking at the (at most 9) heaps for lengths n..=9.
+#[derive(Debug, Clone)]
+struct Disk {
+    size: usize,
+    /// all spans occupied by a file, indexed by file id. Only `compact_blocks` fragments files.
+    files: Vec<Vec<Span>>,
+    free: [BinaryHeap<Reverse<usize>>; 10],
+}
 
-            right_file = right_file_iter.next().cloned().unwrap();
-            if left_file.id == right_file.id {
-                // TODO handle leftover blocks. This is not an issue for my input
-                break 'outer;
+impl Disk {
+    fn new(layout: &[File]) -> Self {
+        let mut files = Vec::with_capacity(layout.len());
+        let mut free: [BinaryHeap<Reverse<usize>>; 10] = Default::default();
+        let mut position = 0;
+        for file in layout {
+            files.push(vec![Span {
+                start: position,
+                len: file.len,
+            }]);
+            position += file.len as usize;
+            if file.trailing_space > 0 {
+                free[file.trailing_space as usize].push(Reverse(position));
+                position += file.trailing_space as usize;
             }
         }
+        Self {
+            size: position,
+            files,
+            free,
+        }
+    }
 
-        blocks.extend_from_slice(&vec![right_file.id; space as usize]);
-        right_file.len -= space;
+    /// find the leftmost gap with at least `min_len` blocks that starts before `before`
+    fn leftmost_free(&self, min_len: u8, before: usize) -> Option<(usize, u8)> {
+        (min_len.max(1)..=9)
+            .filter_map(|len| {
+                let Reverse(start) = self.free[len as usize].peek()?;
+                Some((*start, len))
+            })
+            .filter(|(start, _)| *start < before)
+            .min()
     }
-    blocks.iter().enumerate().map(|(pos, id)| pos * id).sum()
-}
 
-fn part2(files: &[File]) -> usize {
-    let mut disk: Vec<usize> = Vec::with_capacity(
-        files
-            .iter()
-            .map(|f| (f.len + f.trailing_space) as usize)
-            .sum(),
-    );
-
-    let mut initial_position = Vec::with_capacity(files.len());
-    for file in files {
-        initial_position.push(disk.len());
-        disk.extend_from_slice(&vec![file.id; file.len as usize]);
-        disk.extend_from_slice(&vec![BLANK; file.trailing_space as usize]);
+    /// take the first `len` blocks of a gap that was found by `leftmost_free`
+    fn allocate(&mut self, start: usize, gap: u8, len: u8) {
+        let Reverse(popped) = self.free[gap as usize].pop().unwrap();
+        assert_eq!(popped, start);
+        if gap > len {
+            self.free[(gap - len) as usize].push(Reverse(start + len as usize));
+        }
     }
 
-    for (id, pos) in initial_position.iter().enumerate().rev() {
-        let len = files[id].len;
-        let mut streak = 0;
-        let mut move_to = BLANK;
-        for (i, value) in disk.iter().enumerate() {
-            if i == *pos {
-                break; // don't move files to the right
+    /// move single blocks from the end of the disk into the leftmost gaps
+    fn compact_blocks(&mut self) {
+        for id in (0..self.files.len()).rev() {
+            // files that we haven't processed yet still consist of a single span
+            let mut remaining = self.files[id][0];
+            while remaining.len > 0 {
+                let Some((start, gap)) = self.leftmost_free(1, remaining.start) else {
+                    break; // all gaps are right of this file
+                };
+                let moved = gap.min(remaining.len);
+                self.allocate(start, gap, moved);
+                self.files[id].push(Span { start, len: moved });
+                remaining.len -= moved; // blocks are taken from the end of the file
             }
-            if *value == BLANK {
-                streak += 1;
-                if streak == len {
-                    move_to = 1 + i - len as usize;
-                    break; // found the leftmost free space
-                }
-            } else {
-                streak = 0;
+            self.files[id][0] = remaining;
+        }
+    }
+
+    /// move whole files into the leftmost gap that is large enough
+    fn compact_files(&mut self) {
+        // Freed up space is never re-used, because it is right of all files that are still
+        // going to be moved.
+        for id in (0..self.files.len()).rev() {
+            let span = self.files[id][0];
+            if let Some((start, gap)) = self.leftmost_free(span.len, span.start) {
+                self.allocate(start, gap, span.len);
+                self.files[id][0].start = start;
             }
         }
-        if move_to != BLANK {
-            // erase old values
-            // and set new values
-            for i in 0..len as usize {
-                disk[pos + i] = BLANK;
-                assert_eq!(disk[move_to + i], BLANK);
-                disk[move_to + i] = id;
+    }
+
+    fn checksum(&self) -> usize {
+        let mut result = 0;
+        for (id, spans) in self.files.iter().enumerate() {
+            for span in spans {
+                result += id * (span.start..span.start + span.len as usize).sum::<usize>();
             }
         }
+        result
+    }
+}
+
+/// block-level rendering like in the puzzle description (`00...111...2`).
+/// File ids are printed modulo 10.
+impl fmt::Display for Disk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut spans: Vec<(usize, Span)> = self
+            .files
+            .iter()
+            .enumerate()
+            .flat_map(|(id, spans)| spans.iter().map(move |span| (id, *span)))
+            .filter(|(_, span)| span.len > 0)
+            .collect();
+        spans.sort_unstable_by_key(|(_, span)| span.start);
+
+        let mut position = 0;
+        for (id, span) in spans {
+            write!(f, "{}", ".".repeat(span.start - position))?;
+            let digit = char::from_digit((id % 10) as u32, 10).unwrap();
+            write!(f, "{}", digit.to_string().repeat(span.len as usize))?;
+            position = span.start + span.len as usize;
+        }
+        write!(f, "{}", ".".repeat(self.size - position))
     }
+}
 
-    disk.iter()
-        .enumerate()
-        .filter_map(|(i, &v)| if v == BLANK { None } else { Some(i * v) })
-        .sum()
+fn part1(files: &[File]) -> usize {
+    let mut disk = Disk::new(files);
+    disk.compact_blocks();
+    disk.checksum()
+}
+
+fn part2(files: &[File]) -> usize {
+    let mut disk = Disk::new(files);
+    disk.compact_files();
+    disk.checksum()
 }
 
 pub fn main(input: &Path) -> (usize, usize) {
     let files = read_disk(input);
     (part1(&files), part2(&files))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(layout: &str) -> Disk {
+        Disk::new(&parse_layout(layout))
+    }
+
+    #[test]
+    fn sample() {
+        let files = parse_layout("2333133121414131402\n");
+        assert_eq!(part1(&files), 1928);
+        assert_eq!(part2(&files), 2858);
+    }
+
+    #[test]
+    fn render_blocks() {
+        let mut disk = layout("12345");
+        assert_eq!(disk.to_string(), "0..111....22222");
+        disk.compact_blocks();
+        assert_eq!(disk.to_string(), "022111222......");
+        assert_eq!(
+            layout("2333133121414131402").to_string(),
+            "00...111...2...333.44.5555.6666.777.888899"
+        );
+    }
+
+    #[test]
+    fn render_files() {
+        let mut disk = layout("2333133121414131402");
+        disk.compact_files();
+        assert_eq!(
+            disk.to_string(),
+            "00992111777.44.333....5555.6666.....8888.."
+        );
+    }
+
+    #[test]
+    fn last_file_only_partly_fits() {
+        let mut blocks = layout("123");
+        blocks.compact_blocks();
+        assert_eq!(blocks.to_string(), "0111..");
+        assert_eq!(blocks.checksum(), 1 + 2 + 3);
+
+        // whole files stay where they are
+        let mut files = layout("123");
+        files.compact_files();
+        assert_eq!(files.to_string(), "0..111");
+        assert_eq!(files.checksum(), 3 + 4 + 5);
+    }
+
+    #[test]
+    fn leftover_blocks_stay_in_place() {
+        // more free space than blocks to move, and a gap right of the last file
+        let mut disk = layout("1333");
+        assert_eq!(disk.to_string(), "0...111...");
+        disk.compact_blocks();
+        assert_eq!(disk.to_string(), "0111......");
+        assert_eq!(disk.checksum(), 1 + 2 + 3);
+    }
+
+    #[test]
+    fn ids_are_rendered_modulo_10() {
+        assert_eq!(
+            layout("10101010101010101010101").to_string(),
+            "012345678901"
+        );
+    }
+}
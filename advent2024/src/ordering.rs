@@ -0,0 +1,229 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fmt,
+    hash::Hash,
+};
+
+/// The rules contradict each other: every member has to come before the next one and the last
+/// one has to come before the first one again.
+#[derive(Debug, PartialEq, Eq)]
+pub struct CycleError<T> {
+    pub members: Vec<T>,
+}
+
+/// e.g. `1 before 2 before 3 before 1`
+impl<T: fmt::Display> fmt::Display for CycleError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for member in &self.members {
+            write!(f, "{member} before ")?;
+        }
+        match self.members.first() {
+            Some(first) => write!(f, "{first}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A precedence relation given as a set of "a must come before b" rules
+#[derive(Debug, Clone)]
+pub struct Precedence<T> {
+    successors: HashMap<T, HashSet<T>>,
+}
+
+impl<T> Default for Precedence<T> {
+    fn default() -> Self {
+        Self {
+            successors: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Copy + Eq + Hash> Precedence<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_rule(&mut self, before: T, after: T) {
+        self.successors.entry(before).or_default().insert(after);
+    }
+
+    pub fn must_precede(&self, before: &T, after: &T) -> bool {
+        self.successors
+            .get(before)
+            .is_some_and(|successors| successors.contains(after))
+    }
+
+    /// Items without a rule between them compare as equal, so this is only a total order if the
+    /// rules restricted to the compared items are complete and free of cycles. Use `sort` to
+    /// sort by these rules.
+    pub fn compare(&self, a: &T, b: &T) -> Ordering {
+        if self.must_precede(a, b) {
+            Ordering::Less
+        } else if self.must_precede(b, a) {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }
+    }
+
+    /// check that no item comes after an item it has to precede (adjacent pairs only)
+    pub fn is_sorted(&self, items: &[T]) -> bool {
+        items
+            .windows(2)
+            .all(|pair| !self.must_precede(&pair[1], &pair[0]))
+    }
+
+    /// Sort the items in O(n log n). This needs a rule between every pair of items, like in
+    /// 2024 day 5, use `topological_sort` for sparse rules.
+    ///
+    /// Contradicting rules are detected if they end up between neighbours after sorting, and
+    /// then `topological_sort` finds the cycle. A contradiction between items that aren't
+    /// neighbours can go unnoticed, since checking all pairs would cost O(n²).
+    pub fn sort(&self, items: &mut [T]) -> Result<(), CycleError<T>> {
+        items.sort_unstable_by(|a, b| self.compare(a, b));
+        if !self.is_sorted(items) {
+            let sorted = self.topological_sort(items)?;
+            items.copy_from_slice(&sorted);
+        }
+        Ok(())
+    }
+
+    /// Kahn's algorithm restricted to the given items, O(n²) rule lookups.
+    ///
+    /// Items that are not constrained by any rule keep their original order. Duplicates are
+    /// allowed and stay next to each other only if the rules demand it.
+    pub fn topological_sort(&self, items: &[T]) -> Result<Vec<T>, CycleError<T>> {
+        // keyed by position, so that duplicates are counted separately
+        let mut in_degree = vec![0; items.len()];
+        for before in items {
+            for (j, after) in items.iter().enumerate() {
+                if self.must_precede(before, after) {
+                    in_degree[j] += 1;
+                }
+            }
+        }
+
+        let mut ready: Vec<usize> = (0..items.len())
+            .rev()
+            .filter(|i| in_degree[*i] == 0)
+            .collect();
+        let mut result = Vec::with_capacity(items.len());
+        while let Some(i) = ready.pop() {
+            result.push(items[i]);
+            for (j, after) in items.iter().enumerate().rev() {
+                if self.must_precede(&items[i], after) {
+                    in_degree[j] -= 1;
+                    if in_degree[j] == 0 {
+                        ready.push(j);
+                    }
+                }
+            }
+        }
+
+        if result.len() == items.len() {
+            Ok(result)
+        } else {
+            let remaining: Vec<usize> = (0..items.len()).filter(|i| in_degree[*i] > 0).collect();
+            Err(self.find_cycle(items, &remaining))
+        }
+    }
+
+    /// every remaining position still has a remaining predecessor, so walking backwards has to
+    /// run into a cycle eventually
+    fn find_cycle(&self, items: &[T], remaining: &[usize]) -> CycleError<T> {
+        let mut path = vec![remaining[0]];
+        loop {
+            let current = &items[*path.last().unwrap()];
+            let predecessor = *remaining
+                .iter()
+                .find(|candidate| self.must_precede(&items[**candidate], current))
+                .unwrap();
+            if let Some(start) = path.iter().position(|i| *i == predecessor) {
+                let members = path[start..].iter().rev().map(|i| items[*i]).collect();
+                return CycleError { members };
+            }
+            path.push(predecessor);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn precedence(pairs: &[(u32, u32)]) -> Precedence<u32> {
+        let mut result = Precedence::new();
+        for (before, after) in pairs {
+            result.add_rule(*before, *after);
+        }
+        result
+    }
+
+    #[test]
+    fn sorts_with_complete_rules() {
+        let rules = precedence(&[(1, 2), (1, 3), (2, 3)]);
+        let mut items = [3, 1, 2];
+        assert!(!rules.is_sorted(&items));
+        rules.sort(&mut items).unwrap();
+        assert_eq!(items, [1, 2, 3]);
+        assert!(rules.is_sorted(&items));
+    }
+
+    #[test]
+    fn sorts_with_sparse_rules() {
+        let rules = precedence(&[(3, 1)]);
+        assert_eq!(rules.topological_sort(&[1, 2, 3]), Ok(vec![2, 3, 1]));
+        assert_eq!(rules.topological_sort(&[4, 5]), Ok(vec![4, 5]));
+    }
+
+    #[test]
+    fn reports_cycles() {
+        let rules = precedence(&[(1, 2), (2, 3), (3, 1), (0, 1)]);
+        let err = rules.topological_sort(&[3, 0, 2, 1]).unwrap_err();
+        assert_eq!(err.members.len(), 3);
+        for (i, member) in err.members.iter().enumerate() {
+            let next = err.members[(i + 1) % err.members.len()];
+            assert!(rules.must_precede(member, &next));
+        }
+        assert_eq!(
+            rules.topological_sort(&[1, 2]),
+            Ok(vec![1, 2]),
+            "the cycle needs all of its members"
+        );
+    }
+
+    #[test]
+    fn sort_reports_contradicting_neighbours() {
+        let rules = precedence(&[(1, 2), (2, 1), (0, 1), (0, 2)]);
+        let mut items = [2, 0, 1];
+        let err = rules.sort(&mut items).unwrap_err();
+        assert_eq!(err.to_string().matches("before").count(), 2);
+        assert!(!rules.is_sorted(&[1, 2]) && !rules.is_sorted(&[2, 1]));
+    }
+
+    #[test]
+    fn reports_self_loops() {
+        let rules = precedence(&[(1, 1)]);
+        assert_eq!(
+            rules.topological_sort(&[2, 1]),
+            Err(CycleError { members: vec![1] })
+        );
+    }
+
+    #[test]
+    fn keeps_duplicates() {
+        let rules = precedence(&[(1, 2)]);
+        assert_eq!(rules.topological_sort(&[2, 1, 2]), Ok(vec![1, 2, 2]));
+        assert_eq!(rules.topological_sort(&[1, 2, 2]), Ok(vec![1, 2, 2]));
+        let rules = precedence(&[(1, 2), (2, 1)]);
+        assert_eq!(
+            rules
+                .topological_sort(&[1, 2, 2])
+                .unwrap_err()
+                .members
+                .len(),
+            2
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! Multisets for frequency-based puzzles
+
+use std::{
+    cmp::{min, Reverse},
+    collections::HashMap,
+    hash::Hash,
+    marker::PhantomData,
+    ops::{Add, Sub},
+};
+
+/// Counts how often each item occurs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counter<T: Eq + Hash> {
+    counts: HashMap<T, usize>,
+}
+
+impl<T: Eq + Hash> Default for Counter<T> {
+    fn default() -> Self {
+        Self {
+            counts: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Eq + Hash> Counter<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, item: T) {
+        self.insert_n(item, 1);
+    }
+
+    pub fn insert_n(&mut self, item: T, n: usize) {
+        if n > 0 {
+            *self.counts.entry(item).or_default() += n;
+        }
+    }
+
+    /// forget about an item and return how often it occurred
+    pub fn remove(&mut self, item: &T) -> usize {
+        self.counts.remove(item).unwrap_or(0)
+    }
+
+    pub fn get(&self, item: &T) -> usize {
+        self.counts.get(item).cloned().unwrap_or(0)
+    }
+
+    /// number of distinct items
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// number of items including repetitions
+    pub fn total(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&T, usize)> {
+        self.counts.iter().map(|(item, count)| (item, *count))
+    }
+
+    /// the `n` most frequent items, most frequent first. The order of ties is unspecified.
+    pub fn most_common(&self, n: usize) -> Vec<(&T, usize)> {
+        let mut result: Vec<(&T, usize)> = self.iter().collect();
+        result.sort_unstable_by_key(|(_, count)| Reverse(*count));
+        result.truncate(n);
+        result
+    }
+
+    /// all counts in descending order, e.g. `[3, 2]` for a full house
+    pub fn signature(&self) -> Vec<usize> {
+        let mut result: Vec<usize> = self.counts.values().cloned().collect();
+        result.sort_unstable_by(|a, b| b.cmp(a));
+        result
+    }
+
+    /// items that occur in both multisets, with the smaller of both counts
+    pub fn intersection(&self, other: &Self) -> Self
+    where
+        T: Clone,
+    {
+        let mut result = Self::new();
+        for (item, count) in self.iter() {
+            result.insert_n(item.clone(), min(count, other.get(item)));
+        }
+        result
+    }
+}
+
+impl<T: Eq + Hash> FromIterator<T> for Counter<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut result = Self::new();
+        result.extend(iter);
+        result
+    }
+}
+
+impl<T: Eq + Hash> Extend<T> for Counter<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.insert(item);
+        }
+    }
+}
+
+/// sum of both multisets
+impl<T: Eq + Hash> Add for Counter<T> {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self {
+        for (item, count) in rhs.counts {
+            self.insert_n(item, count);
+        }
+        self
+    }
+}
+
+/// difference of both multisets; counts that would drop below 1 are removed
+impl<T: Eq + Hash> Sub for Counter<T> {
+    type Output = Self;
+
+    fn sub(mut self, rhs: Self) -> Self {
+        for (item, count) in rhs.counts {
+            if let Some(own) = self.counts.get_mut(&item) {
+                if *own > count {
+                    *own -= count;
+                } else {
+                    self.counts.remove(&item);
+                }
+            }
+        }
+        self
+    }
+}
+
+/// Counter for small keys like fieldless enums, which convert to an index below `N`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArrayCounter<T, const N: usize> {
+    counts: [usize; N],
+    _keys: PhantomData<T>,
+}
+
+impl<T, const N: usize> Default for ArrayCounter<T, N> {
+    fn default() -> Self {
+        Self {
+            counts: [0; N],
+            _keys: PhantomData,
+        }
+    }
+}
+
+impl<T: Into<usize>, const N: usize> ArrayCounter<T, N> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, item: T) {
+        self.counts[item.into()] += 1;
+    }
+
+    /// forget about an item and return how often it occurred
+    pub fn remove(&mut self, item: T) -> usize {
+        std::mem::take(&mut self.counts[item.into()])
+    }
+
+    pub fn get(&self, item: T) -> usize {
+        self.counts[item.into()]
+    }
+
+    /// number of items including repetitions
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// all non-zero counts in descending order, e.g. `[3, 2]` for a full house
+    pub fn signature(&self) -> Vec<usize> {
+        let mut result: Vec<usize> = self.counts.iter().cloned().filter(|c| *c > 0).collect();
+        result.sort_unstable_by(|a, b| b.cmp(a));
+        result
+    }
+}
+
+impl<T: Into<usize>, const N: usize> FromIterator<T> for ArrayCounter<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut result = Self::new();
+        for item in iter {
+            result.insert(item);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts() {
+        let mut counter: Counter<char> = "abracadabra".chars().collect();
+        assert_eq!(counter.get(&'a'), 5);
+        assert_eq!(counter.get(&'z'), 0);
+        assert_eq!((counter.len(), counter.total()), (5, 11));
+        counter.insert_n('z', 0);
+        assert_eq!(counter.len(), 5);
+        assert_eq!(counter.remove(&'b'), 2);
+        assert_eq!(counter.remove(&'b'), 0);
+        assert_eq!((counter.len(), counter.total()), (4, 9));
+    }
+
+    #[test]
+    fn most_common_and_signature() {
+        let counter: Counter<char> = "abracadabra".chars().collect();
+        assert_eq!(counter.most_common(1), [(&'a', 5)]);
+        let top3 = counter.most_common(3);
+        assert_eq!(top3.len(), 3);
+        assert_eq!(top3[0], (&'a', 5));
+        // b and r are tied
+        assert!(top3[1..].iter().all(|(_, count)| *count == 2));
+        assert_eq!(counter.most_common(10).len(), 5);
+        assert!(counter.most_common(0).is_empty());
+
+        assert_eq!(counter.signature(), [5, 2, 2, 1, 1]);
+        let full_house: Counter<char> = "KKQQK".chars().collect();
+        assert_eq!(full_house.signature(), [3, 2]);
+        assert!(Counter::<char>::new().signature().is_empty());
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a: Counter<char> = "aaabbc".chars().collect();
+        let b: Counter<char> = "abbbd".chars().collect();
+
+        let sum = a.clone() + b.clone();
+        assert_eq!(sum, "aaaabbbbbcd".chars().collect());
+
+        // b drops to 0 and d was never there
+        let difference = a.clone() - b.clone();
+        assert_eq!(difference, "aac".chars().collect());
+        assert_eq!(difference.get(&'b'), 0);
+        assert_eq!(difference.len(), 2);
+        assert!((a.clone() - a.clone()).is_empty());
+
+        let common = a.intersection(&b);
+        assert_eq!(common, "abb".chars().collect());
+        assert!(a.intersection(&Counter::new()).is_empty());
+    }
+
+    #[test]
+    fn array_counter() {
+        let mut counter: ArrayCounter<u8, 4> = [3, 1, 3, 3, 0].into_iter().collect();
+        assert_eq!(counter.get(3), 3);
+        assert_eq!(counter.get(2), 0);
+        assert_eq!(counter.total(), 5);
+        assert_eq!(counter.signature(), [3, 1, 1]);
+        assert_eq!(counter.remove(3), 3);
+        assert_eq!(counter.remove(3), 0);
+        counter.insert(2);
+        assert_eq!(counter.signature(), [1, 1, 1]);
+        assert_eq!(counter.total(), 3);
+    }
+}
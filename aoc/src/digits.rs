@@ -0,0 +1,171 @@
+//! Decimal digit manipulation with integer arithmetic instead of `to_string` round-trips
+
+/// number of decimal digits (0 has one digit)
+pub fn num_digits(n: usize) -> u32 {
+    n.checked_ilog10().map_or(1, |log| log + 1)
+}
+
+/// `None` if the result doesn't fit into a `usize`
+pub fn pow10(exp: u32) -> Option<usize> {
+    10usize.checked_pow(exp)
+}
+
+/// glue the digits of `b` to the end of `a`, e.g. `concat(12, 345) == Some(12345)`. `None` on
+/// overflow.
+pub fn concat(a: usize, b: usize) -> Option<usize> {
+    a.checked_mul(pow10(num_digits(b))?)?.checked_add(b)
+}
+
+/// inverse of `concat`: `strip_suffix_digits(12345, 345) == Some(12)`
+pub fn strip_suffix_digits(a: usize, b: usize) -> Option<usize> {
+    let Some(shift) = pow10(num_digits(b)) else {
+        // `b` has as many digits as the largest `usize`, so nothing can be left
+        return (a == b).then_some(0);
+    };
+    if a % shift == b {
+        Some(a / shift)
+    } else {
+        None
+    }
+}
+
+/// split a number with an even number of digits into its left and right half,
+/// e.g. `split_digits_half(1000) == Some((10, 0))`
+pub fn split_digits_half(n: usize) -> Option<(usize, usize)> {
+    let len = num_digits(n);
+    if len.is_multiple_of(2) {
+        let shift = pow10(len / 2).unwrap(); // at most 10^10
+        Some((n / shift, n % shift))
+    } else {
+        None
+    }
+}
+
+/// build a number from its digits, most significant digit first
+pub fn from_digits<I: IntoIterator<Item = u8>>(digits: I) -> usize {
+    digits
+        .into_iter()
+        .fold(0, |acc, digit| acc * 10 + digit as usize)
+}
+
+/// iterate over the digits of `n`, most significant digit first. Use `.rev()` for the other way
+/// round.
+pub fn digits(n: usize) -> Digits {
+    Digits {
+        n,
+        front: 0,
+        back: num_digits(n),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Digits {
+    n: usize,
+    /// position of the next digit from the left
+    front: u32,
+    /// one past the position of the next digit from the right
+    back: u32,
+}
+
+impl Digits {
+    fn digit_at(&self, position: u32) -> u8 {
+        let len = num_digits(self.n);
+        ((self.n / pow10(len - 1 - position).unwrap()) % 10) as u8
+    }
+}
+
+impl Iterator for Digits {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.front == self.back {
+            None
+        } else {
+            self.front += 1;
+            Some(self.digit_at(self.front - 1))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.back - self.front) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl DoubleEndedIterator for Digits {
+    fn next_back(&mut self) -> Option<u8> {
+        if self.front == self.back {
+            None
+        } else {
+            self.back -= 1;
+            Some(self.digit_at(self.back))
+        }
+    }
+}
+
+impl ExactSizeIterator for Digits {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn number_of_digits() {
+        assert_eq!(num_digits(0), 1);
+        assert_eq!(num_digits(9), 1);
+        assert_eq!(num_digits(10), 2);
+        assert_eq!(num_digits(usize::MAX), 20);
+        assert_eq!(pow10(19), Some(10_000_000_000_000_000_000));
+        assert_eq!(pow10(20), None);
+    }
+
+    #[test]
+    fn concat_and_strip() {
+        assert_eq!(concat(12, 345), Some(12345));
+        assert_eq!(concat(12, 0), Some(120));
+        assert_eq!(concat(0, 7), Some(7));
+        assert_eq!(concat(1, pow10(19).unwrap()), None);
+        assert_eq!(concat(usize::MAX / 10, 9), None);
+
+        assert_eq!(strip_suffix_digits(12345, 345), Some(12));
+        assert_eq!(strip_suffix_digits(12345, 45), Some(123));
+        assert_eq!(strip_suffix_digits(12345, 44), None);
+        assert_eq!(strip_suffix_digits(120, 0), Some(12));
+        assert_eq!(strip_suffix_digits(42, 42), Some(0));
+        assert_eq!(strip_suffix_digits(usize::MAX, usize::MAX), Some(0));
+        assert_eq!(strip_suffix_digits(usize::MAX - 1, usize::MAX), None);
+        assert_eq!(strip_suffix_digits(5, pow10(19).unwrap()), None);
+    }
+
+    #[test]
+    fn split_in_half() {
+        assert_eq!(split_digits_half(1000), Some((10, 0)));
+        assert_eq!(split_digits_half(1234), Some((12, 34)));
+        assert_eq!(split_digits_half(0), None);
+        assert_eq!(split_digits_half(123), None);
+        assert_eq!(
+            split_digits_half(usize::MAX),
+            Some((1844674407, 3709551615))
+        );
+    }
+
+    #[test]
+    fn iterate_from_both_ends() {
+        assert_eq!(digits(0).collect::<Vec<_>>(), [0]);
+        assert_eq!(digits(1203).collect::<Vec<_>>(), [1, 2, 0, 3]);
+        assert_eq!(digits(1203).rev().collect::<Vec<_>>(), [3, 0, 2, 1]);
+        assert_eq!(from_digits(digits(usize::MAX)), usize::MAX);
+
+        let mut it = digits(12345);
+        assert_eq!(it.len(), 5);
+        assert_eq!(it.next(), Some(1));
+        assert_eq!(it.next_back(), Some(5));
+        assert_eq!(it.next_back(), Some(4));
+        assert_eq!(it.len(), 2);
+        assert_eq!(it.next(), Some(2));
+        assert_eq!(it.next(), Some(3));
+        assert_eq!(it.next_back(), None);
+        assert_eq!(it.next(), None);
+        assert_eq!(it.len(), 0);
+    }
+}
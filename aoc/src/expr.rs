@@ -0,0 +1,614 @@
+//! Integer arithmetic expressions with named variables
+
+use std::{fmt, str::FromStr};
+
+/// Integer types that expressions can be evaluated with. All arithmetic is checked.
+pub trait Number: Copy + PartialEq {
+    fn from_i64(value: i64) -> Option<Self>;
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+    fn checked_div(self, rhs: Self) -> Option<Self>;
+}
+
+macro_rules! impl_number {
+    ($($t:ty),*) => {
+        $(
+            impl Number for $t {
+                fn from_i64(value: i64) -> Option<Self> {
+                    value.try_into().ok()
+                }
+
+                fn checked_add(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_add(self, rhs)
+                }
+
+                fn checked_sub(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_sub(self, rhs)
+                }
+
+                fn checked_mul(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_mul(self, rhs)
+                }
+
+                fn checked_div(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_div(self, rhs)
+                }
+            }
+        )*
+    };
+}
+
+impl_number!(i32, i64, i128, isize, u32, u64, u128, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl Op {
+    fn precedence(&self) -> u8 {
+        match self {
+            Op::Add | Op::Sub => 1,
+            Op::Mul | Op::Div => 2,
+        }
+    }
+
+    fn symbol(&self) -> char {
+        match self {
+            Op::Add => '+',
+            Op::Sub => '-',
+            Op::Mul => '*',
+            Op::Div => '/',
+        }
+    }
+
+    fn apply<N: Number>(&self, left: N, right: N) -> Result<N, EvalError> {
+        match self {
+            Op::Add => left.checked_add(right),
+            Op::Sub => left.checked_sub(right),
+            Op::Mul => left.checked_mul(right),
+            Op::Div => {
+                if N::from_i64(0) == Some(right) {
+                    return Err(EvalError::DivisionByZero);
+                }
+                left.checked_div(right)
+            }
+        }
+        .ok_or(EvalError::Overflow)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Constant(i64),
+    Variable(String),
+    Binary(Box<Expr>, Op, Box<Expr>),
+}
+
+impl Default for Expr {
+    fn default() -> Self {
+        Expr::Constant(0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalError {
+    UnknownVariable(String),
+    /// the result (or an intermediate result) does not fit into the number type
+    Overflow,
+    DivisionByZero,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolveError {
+    /// the unknown has to occur exactly once
+    NotLinear,
+    /// e.g. `2 * x = 5`
+    NoIntegerSolution,
+    Eval(EvalError),
+}
+
+impl From<EvalError> for SolveError {
+    fn from(err: EvalError) -> Self {
+        SolveError::Eval(err)
+    }
+}
+
+impl Expr {
+    pub fn binary(left: Expr, op: Op, right: Expr) -> Self {
+        Expr::Binary(Box::new(left), op, Box::new(right))
+    }
+
+    /// evaluate the expression, looking up the values of variables with `env`
+    pub fn eval<N, F>(&self, env: &F) -> Result<N, EvalError>
+    where
+        N: Number,
+        F: Fn(&str) -> Option<N>,
+    {
+        match self {
+            Expr::Constant(c) => N::from_i64(*c).ok_or(EvalError::Overflow),
+            Expr::Variable(name) => {
+                env(name).ok_or_else(|| EvalError::UnknownVariable(name.clone()))
+            }
+            Expr::Binary(left, op, right) => op.apply(left.eval(env)?, right.eval(env)?),
+        }
+    }
+
+    /// evaluate an expression with a single variable
+    pub fn eval_with<N: Number>(&self, name: &str, value: N) -> Result<N, EvalError> {
+        self.eval(&|var| if var == name { Some(value) } else { None })
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        match self {
+            Expr::Constant(_) => false,
+            Expr::Variable(var) => var == name,
+            Expr::Binary(left, _, right) => left.contains(name) || right.contains(name),
+        }
+    }
+
+    /// replace variables by other expressions, e.g. to resolve references between definitions
+    pub fn substitute<F: Fn(&str) -> Option<Expr>>(&self, lookup: &F) -> Expr {
+        match self {
+            Expr::Constant(_) => self.clone(),
+            Expr::Variable(name) => match lookup(name) {
+                Some(expr) => expr.substitute(lookup),
+                None => self.clone(),
+            },
+            Expr::Binary(left, op, right) => {
+                Expr::binary(left.substitute(lookup), *op, right.substitute(lookup))
+            }
+        }
+    }
+
+    /// evaluate all sub-expressions without variables. Sub-expressions that overflow or divide
+    /// by zero are kept as they are.
+    pub fn fold(&self) -> Expr {
+        match self {
+            Expr::Binary(left, op, right) => {
+                let left = left.fold();
+                let right = right.fold();
+                if let (Expr::Constant(a), Expr::Constant(b)) = (&left, &right) {
+                    if let Ok(value) = op.apply(*a, *b) {
+                        return Expr::Constant(value);
+                    }
+                }
+                Expr::binary(left, *op, right)
+            }
+            _ => self.clone(),
+        }
+    }
+
+    /// Find the value of `unknown` so that the expression evaluates to `target`.
+    ///
+    /// The unknown must occur exactly once and everything else must be constant, so that each
+    /// operation on the path to the unknown can be inverted.
+    pub fn solve(&self, target: i64, unknown: &str) -> Result<i64, SolveError> {
+        let constant = |expr: &Expr| expr.eval::<i64, _>(&|_| None);
+        let mut expr = self.fold();
+        let mut target = target;
+        loop {
+            match expr {
+                Expr::Variable(name) if name == unknown => return Ok(target),
+                Expr::Binary(left, op, right) => {
+                    match (left.contains(unknown), right.contains(unknown)) {
+                        (true, false) => {
+                            // x op b = target
+                            let b = constant(&right)?;
+                            target = match op {
+                                Op::Add => Op::Sub.apply(target, b)?,
+                                Op::Sub => Op::Add.apply(target, b)?,
+                                Op::Mul => exact_div(target, b)?,
+                                // integer division: the smallest x that works
+                                Op::Div => {
+                                    if b == 0 {
+                                        return Err(EvalError::DivisionByZero.into());
+                                    }
+                                    Op::Mul.apply(target, b)?
+                                }
+                            };
+                            expr = *left;
+                        }
+                        (false, true) => {
+                            // a op x = target
+                            let a = constant(&left)?;
+                            target = match op {
+                                Op::Add => Op::Sub.apply(target, a)?,
+                                Op::Sub => Op::Sub.apply(a, target)?,
+                                Op::Mul => exact_div(target, a)?,
+                                Op::Div => {
+                                    let x = Op::Div
+                                        .apply(a, target)
+                                        .map_err(|_| SolveError::NoIntegerSolution)?;
+                                    if Op::Div.apply(a, x) != Ok(target) {
+                                        return Err(SolveError::NoIntegerSolution);
+                                    }
+                                    x
+                                }
+                            };
+                            expr = *right;
+                        }
+                        _ => return Err(SolveError::NotLinear),
+                    }
+                }
+                _ => return Err(SolveError::NotLinear),
+            }
+        }
+    }
+}
+
+fn exact_div(a: i64, b: i64) -> Result<i64, SolveError> {
+    let quotient = Op::Div
+        .apply(a, b)
+        .map_err(|_| SolveError::NoIntegerSolution)?;
+    if quotient * b == a {
+        Ok(quotient)
+    } else {
+        Err(SolveError::NoIntegerSolution)
+    }
+}
+
+/// prints as few parentheses as possible
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Constant(c) => write!(f, "{c}"),
+            Expr::Variable(name) => write!(f, "{name}"),
+            Expr::Binary(left, op, right) => {
+                let precedence = |expr: &Expr| match expr {
+                    Expr::Binary(_, op, _) => op.precedence(),
+                    _ => u8::MAX,
+                };
+                if precedence(left) < op.precedence() {
+                    write!(f, "({left})")?;
+                } else {
+                    write!(f, "{left}")?;
+                }
+                write!(f, " {} ", op.symbol())?;
+                // only a + (b + c) and a * (b * c) can drop the parentheses. a - (b - c) and
+                // a * (b / c) can't, the latter because integer division truncates.
+                let associative = match right.as_ref() {
+                    Expr::Binary(_, inner, _) => inner == op && matches!(op, Op::Add | Op::Mul),
+                    _ => true,
+                };
+                if precedence(right) < op.precedence()
+                    || precedence(right) == op.precedence() && !associative
+                {
+                    write!(f, "({right})")
+                } else {
+                    write!(f, "{right}")
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// byte offset into the parsed string
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Number(i64),
+    Name(String),
+    Op(Op),
+    Open,
+    Close,
+}
+
+fn tokenize(s: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let mut result = vec![];
+    let mut chars = s.char_indices().peekable();
+    while let Some((position, c)) = chars.next() {
+        let token = match c {
+            ' ' | '\t' => continue,
+            '+' => Token::Op(Op::Add),
+            '-' => Token::Op(Op::Sub),
+            '*' => Token::Op(Op::Mul),
+            '/' => Token::Op(Op::Div),
+            '(' => Token::Open,
+            ')' => Token::Close,
+            _ if c.is_ascii_digit() || c.is_alphabetic() || c == '_' => {
+                let mut end = position + c.len_utf8();
+                while let Some((i, next)) = chars.peek() {
+                    if next.is_alphanumeric() || *next == '_' {
+                        end = i + next.len_utf8();
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let word = &s[position..end];
+                if c.is_ascii_digit() {
+                    Token::Number(word.parse().map_err(|_| ParseError {
+                        position,
+                        message: format!("bad number {word}"),
+                    })?)
+                } else {
+                    Token::Name(word.to_string())
+                }
+            }
+            _ => {
+                return Err(ParseError {
+                    position,
+                    message: format!("unexpected character {c:?}"),
+                })
+            }
+        };
+        result.push((position, token));
+    }
+    Ok(result)
+}
+
+/// recursive descent over the tokens, one function per precedence level
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    index: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(_, token)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.index)
+            .map_or(self.end, |(position, _)| *position)
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            position: self.position(),
+            message: message.to_string(),
+        }
+    }
+
+    fn binary_level(
+        &mut self,
+        ops: [Op; 2],
+        operand: fn(&mut Self) -> Result<Expr, ParseError>,
+    ) -> Result<Expr, ParseError> {
+        let mut result = operand(self)?;
+        while let Some(Token::Op(op)) = self.peek() {
+            let op = *op;
+            if !ops.contains(&op) {
+                break;
+            }
+            self.index += 1;
+            result = Expr::binary(result, op, operand(self)?);
+        }
+        Ok(result)
+    }
+
+    fn sum(&mut self) -> Result<Expr, ParseError> {
+        self.binary_level([Op::Add, Op::Sub], Self::product)
+    }
+
+    fn product(&mut self) -> Result<Expr, ParseError> {
+        self.binary_level([Op::Mul, Op::Div], Self::factor)
+    }
+
+    fn factor(&mut self) -> Result<Expr, ParseError> {
+        let token = self.peek().cloned();
+        self.index += 1;
+        match token {
+            Some(Token::Number(n)) => Ok(Expr::Constant(n)),
+            Some(Token::Name(name)) => Ok(Expr::Variable(name)),
+            Some(Token::Op(Op::Sub)) => match self.factor()? {
+                Expr::Constant(n) => Ok(Expr::Constant(-n)),
+                expr => Ok(Expr::binary(Expr::Constant(0), Op::Sub, expr)),
+            },
+            Some(Token::Open) => {
+                let result = self.sum()?;
+                if self.peek() != Some(&Token::Close) {
+                    return Err(self.error("expected ')'"));
+                }
+                self.index += 1;
+                Ok(result)
+            }
+            _ => {
+                self.index -= 1;
+                Err(self.error("expected a number, a variable or '('"))
+            }
+        }
+    }
+}
+
+impl FromStr for Expr {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            index: 0,
+            end: s.len(),
+        };
+        let result = parser.sum()?;
+        if parser.peek().is_some() {
+            return Err(parser.error("unexpected token"));
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Expr {
+        s.parse().unwrap()
+    }
+
+    fn var(name: &str) -> Expr {
+        Expr::Variable(name.to_string())
+    }
+
+    #[test]
+    fn parses_with_precedence_and_left_associativity() {
+        assert_eq!(
+            parse("a - b - c"),
+            Expr::binary(Expr::binary(var("a"), Op::Sub, var("b")), Op::Sub, var("c"))
+        );
+        assert_eq!(
+            parse("1 + 2 * x"),
+            Expr::binary(
+                Expr::Constant(1),
+                Op::Add,
+                Expr::binary(Expr::Constant(2), Op::Mul, var("x"))
+            )
+        );
+        assert_eq!(parse("-3"), Expr::Constant(-3));
+        assert_eq!(
+            parse("-(x)"),
+            Expr::binary(Expr::Constant(0), Op::Sub, var("x"))
+        );
+    }
+
+    #[test]
+    fn reports_parse_errors_with_position() {
+        assert_eq!("1 + ".parse::<Expr>().unwrap_err().position, 4);
+        assert_eq!("(1 + 2".parse::<Expr>().unwrap_err().position, 6);
+        assert_eq!("1 $ 2".parse::<Expr>().unwrap_err().position, 2);
+        assert_eq!("1 2".parse::<Expr>().unwrap_err().position, 2);
+        assert!("99999999999999999999".parse::<Expr>().is_err());
+    }
+
+    #[test]
+    fn prints_minimal_parentheses() {
+        for s in [
+            "a + b + c",
+            "a * b * c",
+            "a - (b - c)",
+            "a - (b + c)",
+            "a / (b / c)",
+            "a / (b * c)",
+            "a * (b / c)",
+            "a + (b - c)",
+            "(a + b) * c",
+            "a * b + c / d",
+        ] {
+            assert_eq!(parse(s).to_string(), s);
+        }
+        assert_eq!(parse("(a - b) - c").to_string(), "a - b - c");
+        assert_eq!(parse("a + (b + c)").to_string(), "a + b + c");
+    }
+
+    #[test]
+    fn printing_round_trips() {
+        for s in [
+            "a * (b / c)",
+            "a - (b - c) * d",
+            "(a + b) / (c - d)",
+            "0 - x * (y + 1)",
+            "a + -2",
+        ] {
+            let expr = parse(s);
+            assert_eq!(parse(&expr.to_string()), expr, "{s}");
+        }
+        let env = |name: &str| match name {
+            "a" => Some(3),
+            "b" => Some(5),
+            "c" => Some(2),
+            _ => None,
+        };
+        let expr = parse("a * (b / c)");
+        assert_eq!(expr.eval::<i64, _>(&env), Ok(6));
+        assert_eq!(parse(&expr.to_string()).eval::<i64, _>(&env), Ok(6));
+    }
+
+    #[test]
+    fn folds_constants() {
+        assert_eq!(parse("2 * 3 + x * (4 - 1)").fold(), parse("6 + x * 3"));
+        assert_eq!(parse("1 / 0 + x").fold(), parse("1 / 0 + x"));
+        assert_eq!(parse("(1 + 2) * (3 + 4)").fold(), Expr::Constant(21));
+    }
+
+    #[test]
+    fn evaluates() {
+        assert_eq!(parse("7 / 2 - 1").eval_with::<i64>("x", 0), Ok(2));
+        assert_eq!(
+            parse("y").eval_with::<i64>("x", 0),
+            Err(EvalError::UnknownVariable(String::from("y")))
+        );
+        assert_eq!(
+            parse("x / 0").eval_with::<i64>("x", 1),
+            Err(EvalError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn detects_overflow() {
+        assert_eq!(
+            parse("x * 2").eval_with::<i32>("x", i32::MAX),
+            Err(EvalError::Overflow)
+        );
+        assert_eq!(
+            parse("x - 1").eval_with::<u32>("x", 0),
+            Err(EvalError::Overflow)
+        );
+        assert_eq!(
+            parse("5000000000").eval_with::<i32>("x", 0),
+            Err(EvalError::Overflow)
+        );
+        assert_eq!(
+            parse("x * 2").solve(i64::MAX, "x"),
+            Err(SolveError::NoIntegerSolution)
+        );
+        assert_eq!(
+            parse("x - 1").solve(i64::MAX, "x"),
+            Err(SolveError::Eval(EvalError::Overflow))
+        );
+    }
+
+    #[test]
+    fn solves_with_unknown_on_the_left() {
+        assert_eq!(parse("x + 3").solve(10, "x"), Ok(7));
+        assert_eq!(parse("x - 3").solve(10, "x"), Ok(13));
+        assert_eq!(parse("x * 3").solve(12, "x"), Ok(4));
+        assert_eq!(parse("x / 3").solve(4, "x"), Ok(12));
+        assert_eq!(
+            parse("x * 3").solve(10, "x"),
+            Err(SolveError::NoIntegerSolution)
+        );
+        assert_eq!(
+            parse("x / 0").solve(5, "x"),
+            Err(SolveError::Eval(EvalError::DivisionByZero))
+        );
+    }
+
+    #[test]
+    fn solves_with_unknown_on_the_right() {
+        assert_eq!(parse("3 + x").solve(10, "x"), Ok(7));
+        assert_eq!(parse("3 - x").solve(10, "x"), Ok(-7));
+        assert_eq!(parse("3 * x").solve(12, "x"), Ok(4));
+        assert_eq!(parse("12 / x").solve(4, "x"), Ok(3));
+        assert_eq!(
+            parse("12 / x").solve(5, "x"),
+            Err(SolveError::NoIntegerSolution)
+        );
+    }
+
+    #[test]
+    fn solves_nested_expressions() {
+        let expr = parse("(4 + 2 * (x - 3)) / 4");
+        let x = expr.solve(150, "x").unwrap();
+        assert_eq!(expr.eval_with::<i64>("x", x), Ok(150));
+        assert_eq!(parse("x * x").solve(4, "x"), Err(SolveError::NotLinear));
+        assert_eq!(parse("y + 1").solve(4, "x"), Err(SolveError::NotLinear));
+    }
+}
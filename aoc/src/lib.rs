@@ -0,0 +1,9 @@
+//! Helpers that are shared between the years
+
+pub mod counter;
+pub mod digits;
+pub mod expr;
+pub mod memo;
+pub mod ocr;
+pub mod paragraphs;
+pub mod vm;
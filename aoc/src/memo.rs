@@ -0,0 +1,202 @@
+//! Memoization for recursive solvers
+
+use std::{collections::HashMap, hash::Hash, marker::PhantomData};
+
+/// Where a `Memo` keeps its results
+pub trait Store<K, V> {
+    fn lookup(&self, key: &K) -> Option<&V>;
+    fn store(&mut self, key: K, value: V);
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K: Eq + Hash, V> Store<K, V> for HashMap<K, V> {
+    fn lookup(&self, key: &K) -> Option<&V> {
+        self.get(key)
+    }
+
+    fn store(&mut self, key: K, value: V) {
+        self.insert(key, value);
+    }
+
+    fn len(&self) -> usize {
+        HashMap::len(self)
+    }
+}
+
+/// Array-backed store for small integer keys. It grows to the largest key that was stored.
+#[derive(Debug, Clone)]
+pub struct Dense<V> {
+    values: Vec<Option<V>>,
+    len: usize,
+}
+
+impl<V> Dense<V> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut values = Vec::with_capacity(capacity);
+        values.resize_with(capacity, || None);
+        Self { values, len: 0 }
+    }
+}
+
+impl<V> Store<usize, V> for Dense<V> {
+    fn lookup(&self, key: &usize) -> Option<&V> {
+        self.values.get(*key)?.as_ref()
+    }
+
+    fn store(&mut self, key: usize, value: V) {
+        if key >= self.values.len() {
+            self.values.resize_with(key + 1, || None);
+        }
+        if self.values[key].replace(value).is_none() {
+            self.len += 1;
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// Cache for a recursive function.
+///
+/// The `compute` closure of `get_or_compute` receives the memo itself, so a recursive function
+/// keeps its shape: it takes `&mut Memo` as an extra parameter and wraps its body in
+/// `memo.get_or_compute(key, |memo, key| ...)`, passing `memo` along to the recursive calls.
+#[derive(Debug, Clone)]
+pub struct Memo<K, V, S = HashMap<K, V>> {
+    store: S,
+    hits: usize,
+    misses: usize,
+    _types: PhantomData<(K, V)>,
+}
+
+impl<K: Eq + Hash, V> Memo<K, V> {
+    pub fn new() -> Self {
+        Self::with_store(HashMap::new())
+    }
+}
+
+impl<K: Eq + Hash, V> Default for Memo<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Memo<usize, V, Dense<V>> {
+    pub fn dense(capacity: usize) -> Self {
+        Self::with_store(Dense::with_capacity(capacity))
+    }
+}
+
+impl<K, V, S: Store<K, V>> Memo<K, V, S> {
+    pub fn with_store(store: S) -> Self {
+        Self {
+            store,
+            hits: 0,
+            misses: 0,
+            _types: PhantomData,
+        }
+    }
+
+    pub fn get_or_compute<F>(&mut self, key: K, compute: F) -> V
+    where
+        K: Copy,
+        V: Clone,
+        F: FnOnce(&mut Self, K) -> V,
+    {
+        if let Some(value) = self.store.lookup(&key) {
+            self.hits += 1;
+            return value.clone();
+        }
+        self.misses += 1;
+        let value = compute(self, key);
+        self.store.store(key, value.clone());
+        value
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.store.lookup(key)
+    }
+
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    pub fn len(&self) -> usize {
+        self.store.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.store.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fib<S: Store<usize, u64>>(memo: &mut Memo<usize, u64, S>, n: usize) -> u64 {
+        memo.get_or_compute(n, |memo, n| {
+            if n < 2 {
+                n as u64
+            } else {
+                fib(memo, n - 1) + fib(memo, n - 2)
+            }
+        })
+    }
+
+    fn check_fib<S: Store<usize, u64>>(mut memo: Memo<usize, u64, S>) {
+        assert!(memo.is_empty());
+        assert_eq!(fib(&mut memo, 50), 12_586_269_025);
+        // every value is computed once, fib(n - 2) is always known by the time it is needed,
+        // except for fib(0)
+        assert_eq!(memo.misses(), 51);
+        assert_eq!(memo.hits(), 48);
+        assert_eq!(memo.len(), 51);
+        assert_eq!(memo.get(&10), Some(&55));
+        assert_eq!(memo.get(&51), None);
+
+        assert_eq!(fib(&mut memo, 40), 102_334_155);
+        assert_eq!((memo.hits(), memo.misses()), (49, 51));
+    }
+
+    #[test]
+    fn fibonacci_with_hash_map() {
+        check_fib(Memo::new());
+    }
+
+    #[test]
+    fn fibonacci_with_dense_store() {
+        check_fib(Memo::dense(8));
+        check_fib(Memo::dense(0));
+    }
+
+    #[test]
+    fn dense_store() {
+        let mut store = Dense::with_capacity(2);
+        assert!(store.is_empty());
+        store.store(5, 'a');
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.lookup(&5), Some(&'a'));
+        assert_eq!(store.lookup(&4), None);
+        assert_eq!(store.lookup(&100), None);
+
+        // overwriting doesn't change the number of entries
+        store.store(5, 'b');
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.lookup(&5), Some(&'b'));
+
+        store.store(0, 'c');
+        store.store(20, 'd');
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.lookup(&20), Some(&'d'));
+    }
+}
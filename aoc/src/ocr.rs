@@ -0,0 +1,211 @@
+//! Read the block letters that some puzzles draw on a pixel grid
+
+use std::fmt;
+
+/// A font where the glyphs are drawn next to each other, separated by a column of spaces. All
+/// rows of a glyph are equally wide, but glyphs may differ in width.
+pub struct Font {
+    pub height: usize,
+    letters: &'static str,
+    glyphs: &'static str,
+}
+
+/// the 4x6 font (e.g. 2016 day 8, 2019 day 8 and 11, 2021 day 13, 2022 day 10)
+pub const SMALL: Font = Font {
+    height: 6,
+    letters: "ABCEFGHIJKLOPRSUYZ",
+    glyphs: "\
+.##. ###. .##. #### #### .##. #..# .### ..## #..# #... .##. ###. ###. .### #..# #...# ####
+#..# #..# #..# #... #... #..# #..# ..#. ...# #.#. #... #..# #..# #..# #... #..# #...# ...#
+#..# ###. #... ###. ###. #... #### ..#. ...# ##.. #... #..# #..# #..# #... #..# .#.#. ..#.
+#### #..# #... #... #... #.## #..# ..#. ...# #.#. #... #..# ###. ###. .##. #..# ..#.. .#..
+#..# #..# #..# #... #... #..# #..# ..#. #..# #.#. #... #..# #... #.#. ...# #..# ..#.. #...
+#..# ###. .##. #### #... .### #..# .### .##. #..# #### .##. #... #..# ###. .##. ..#.. ####
+",
+};
+
+/// the 6x10 font (e.g. 2018 day 10)
+pub const LARGE: Font = Font {
+    height: 10,
+    letters: "ABCEFGHJKLNPRXZ",
+    glyphs: "\
+..##.. #####. .####. ###### ###### .####. #....# ...### #....# #..... #....# #####. #####. #....# ######
+.#..#. #....# #....# #..... #..... #....# #....# ....#. #...#. #..... ##...# #....# #....# #....# .....#
+#....# #....# #..... #..... #..... #..... #....# ....#. #..#.. #..... ##...# #....# #....# .#..#. .....#
+#....# #....# #..... #..... #..... #..... #....# ....#. #.#... #..... #.#..# #....# #....# .#..#. ....#.
+#....# #####. #..... #####. #####. #..... ###### ....#. ##.... #..... #.#..# #####. #####. ..##.. ...#..
+###### #....# #..... #..... #..... #..### #....# ....#. ##.... #..... #..#.# #..... #..#.. ..##.. ..#...
+#....# #....# #..... #..... #..... #....# #....# ....#. #.#... #..... #..#.# #..... #...#. .#..#. .#....
+#....# #....# #..... #..... #..... #....# #....# #...#. #..#.. #..... #...## #..... #...#. .#..#. #.....
+#....# #....# #....# #..... #..... #...## #....# #...#. #...#. #..... #...## #..... #....# #....# #.....
+#....# #####. .####. ###### #..... .###.# #....# .###.. #....# ###### #....# #..... #....# #....# ######
+",
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OcrError {
+    /// the grid must be exactly as high as the font
+    WrongHeight { expected: usize, found: usize },
+    /// the glyph starting at the given column is not part of the font
+    UnknownGlyph { column: usize, bitmap: Vec<String> },
+}
+
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OcrError::WrongHeight { expected, found } => {
+                write!(f, "expected {expected} rows of pixels, found {found}")
+            }
+            OcrError::UnknownGlyph { column, bitmap } => {
+                writeln!(f, "unknown glyph at column {column}:")?;
+                for row in bitmap {
+                    writeln!(f, "{row}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Font {
+    /// Decode row-major pixels (`true` is lit) of a grid that is `width` pixels wide.
+    ///
+    /// Glyphs are separated by empty columns, so the text may start at any offset and the
+    /// spacing between letters doesn't matter.
+    pub fn decode(&self, pixels: &[bool], width: usize) -> Result<String, OcrError> {
+        let found = pixels.len().checked_div(width).unwrap_or(0);
+        if width == 0 || found != self.height || !pixels.len().is_multiple_of(width) {
+            return Err(OcrError::WrongHeight {
+                expected: self.height,
+                found,
+            });
+        }
+        let column_is_empty = |x: usize| (0..self.height).all(|y| !pixels[y * width + x]);
+
+        let mut result = String::new();
+        let mut x = 0;
+        while x < width {
+            if column_is_empty(x) {
+                x += 1;
+                continue;
+            }
+            let start = x;
+            while x < width && !column_is_empty(x) {
+                x += 1;
+            }
+            let bitmap: Vec<String> = (0..self.height)
+                .map(|y| {
+                    (start..x)
+                        .map(|x| if pixels[y * width + x] { '#' } else { '.' })
+                        .collect()
+                })
+                .collect();
+            match self.lookup(&bitmap) {
+                Some(c) => result.push(c),
+                None => {
+                    return Err(OcrError::UnknownGlyph {
+                        column: start,
+                        bitmap,
+                    })
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    fn lookup(&self, bitmap: &[String]) -> Option<char> {
+        self.letters
+            .chars()
+            .enumerate()
+            .find(|(i, _)| trim_columns(&self.glyph(*i)) == bitmap)
+            .map(|(_, c)| c)
+    }
+
+    /// rows of the i-th glyph
+    fn glyph(&self, i: usize) -> Vec<&'static str> {
+        self.glyphs
+            .lines()
+            .map(|row| row.split(' ').nth(i).unwrap())
+            .collect()
+    }
+}
+
+/// remove empty columns on the left and right side of a glyph
+fn trim_columns(glyph: &[&str]) -> Vec<String> {
+    let width = glyph[0].len();
+    let is_used = |x: usize| glyph.iter().any(|row| row.as_bytes()[x] == b'#');
+    let start = (0..width).find(|x| is_used(*x)).unwrap_or(0);
+    let end = (0..width).rfind(|x| is_used(*x)).map_or(0, |x| x + 1);
+    glyph
+        .iter()
+        .map(|row| row[start..end].to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// draw the text with one empty column between the glyphs
+    fn draw(font: &Font, text: &str) -> (Vec<bool>, usize) {
+        let glyphs: Vec<Vec<&str>> = text
+            .chars()
+            .map(|c| font.glyph(font.letters.find(c).unwrap()))
+            .collect();
+        let rows: Vec<String> = (0..font.height)
+            .map(|y| {
+                let row: Vec<&str> = glyphs.iter().map(|glyph| glyph[y]).collect();
+                row.join(".")
+            })
+            .collect();
+        let width = rows[0].len();
+        let pixels = rows.iter().flat_map(|row| row.chars().map(|c| c == '#'));
+        (pixels.collect(), width)
+    }
+
+    #[test]
+    fn decodes_every_letter() {
+        for font in [SMALL, LARGE] {
+            let (pixels, width) = draw(&font, font.letters);
+            assert_eq!(font.decode(&pixels, width).unwrap(), font.letters);
+        }
+    }
+
+    #[test]
+    fn ignores_spacing() {
+        let (pixels, width) = draw(&SMALL, "HI");
+        let mut padded = vec![];
+        for row in pixels.chunks(width) {
+            padded.extend([false; 3]);
+            padded.extend(row);
+            padded.extend([false; 5]);
+        }
+        assert_eq!(SMALL.decode(&padded, width + 8).unwrap(), "HI");
+        assert_eq!(SMALL.decode(&[false; 6 * 10], 10).unwrap(), "");
+    }
+
+    #[test]
+    fn reports_unknown_glyphs() {
+        let (mut pixels, width) = draw(&SMALL, "AB");
+        // turn the bottom left corner of the B (column 5) off
+        pixels[5 * width + 5] = false;
+        assert_eq!(
+            SMALL.decode(&pixels, width),
+            Err(OcrError::UnknownGlyph {
+                column: 5,
+                bitmap: ["###.", "#..#", "###.", "#..#", "#..#", ".##."]
+                    .map(String::from)
+                    .to_vec(),
+            })
+        );
+    }
+
+    #[test]
+    fn reports_wrong_heights() {
+        let wrong_height = |found| OcrError::WrongHeight { expected: 6, found };
+        assert_eq!(SMALL.decode(&[false; 50], 10), Err(wrong_height(5)));
+        assert_eq!(SMALL.decode(&[false; 61], 10), Err(wrong_height(6)));
+        assert_eq!(SMALL.decode(&[false; 60], 0), Err(wrong_height(0)));
+        assert_eq!(SMALL.decode(&[], 0), Err(wrong_height(0)));
+    }
+}
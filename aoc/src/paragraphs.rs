@@ -0,0 +1,200 @@
+//! Input that consists of blocks of lines, separated by blank lines
+
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Lines};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Yields the lines of every block. Any number of blank lines separates two blocks, so there are
+/// no empty blocks. `\r\n` line endings are removed just like `\n`.
+pub struct Paragraphs<R> {
+    lines: Lines<R>,
+}
+
+impl<R: BufRead> Iterator for Paragraphs<R> {
+    type Item = Vec<String>;
+
+    fn next(&mut self) -> Option<Vec<String>> {
+        let mut block = vec![];
+        for line in self.lines.by_ref() {
+            let line = line.unwrap();
+            if !line.trim().is_empty() {
+                block.push(line);
+            } else if !block.is_empty() {
+                break;
+            }
+        }
+        if block.is_empty() {
+            None
+        } else {
+            Some(block)
+        }
+    }
+}
+
+pub fn paragraphs<R: BufRead>(reader: R) -> Paragraphs<R> {
+    Paragraphs {
+        lines: reader.lines(),
+    }
+}
+
+pub fn from_file<P: AsRef<Path>>(path: P) -> Paragraphs<BufReader<File>> {
+    paragraphs(BufReader::new(File::open(path).unwrap()))
+}
+
+/// A value that is described by a whole block of lines
+pub trait FromBlock: Sized {
+    type Err: fmt::Display;
+
+    fn from_block(lines: &[String]) -> Result<Self, Self::Err>;
+}
+
+/// one value per line
+impl<T> FromBlock for Vec<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    type Err = String;
+
+    fn from_block(lines: &[String]) -> Result<Self, String> {
+        lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                line.trim()
+                    .parse()
+                    .map_err(|err| format!("line {}: {}", i + 1, err))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionError {
+    /// starting at 1, or the number of expected sections if there are too few or too many
+    pub section: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "section {}: {}", self.section, self.message)
+    }
+}
+
+impl<R: BufRead> Paragraphs<R> {
+    fn section<T: FromBlock>(&mut self, section: usize) -> Result<T, SectionError> {
+        let block = self.next().ok_or_else(|| SectionError {
+            section,
+            message: String::from("missing"),
+        })?;
+        T::from_block(&block).map_err(|err| SectionError {
+            section,
+            message: err.to_string(),
+        })
+    }
+
+    fn finish(&mut self, sections: usize) -> Result<(), SectionError> {
+        match self.count() {
+            0 => Ok(()),
+            n => Err(SectionError {
+                section: sections,
+                message: format!("{n} unexpected sections after the last one"),
+            }),
+        }
+    }
+
+    /// parse an input with exactly two sections
+    pub fn sections2<A: FromBlock, B: FromBlock>(mut self) -> Result<(A, B), SectionError> {
+        let a = self.section(1)?;
+        let b = self.section(2)?;
+        self.finish(2)?;
+        Ok((a, b))
+    }
+
+    /// parse an input with exactly three sections
+    pub fn sections3<A: FromBlock, B: FromBlock, C: FromBlock>(
+        mut self,
+    ) -> Result<(A, B, C), SectionError> {
+        let a = self.section(1)?;
+        let b = self.section(2)?;
+        let c = self.section(3)?;
+        self.finish(3)?;
+        Ok((a, b, c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocks(input: &str) -> Vec<Vec<String>> {
+        paragraphs(input.as_bytes()).collect()
+    }
+
+    #[test]
+    fn separated_by_blank_lines() {
+        assert_eq!(blocks("a\nb\n\nc\n"), [vec!["a", "b"], vec!["c"]]);
+        assert_eq!(blocks("a\r\nb\r\n\r\nc\r\n"), [vec!["a", "b"], vec!["c"]]);
+        // several blank lines, also around the blocks and with whitespace
+        assert_eq!(
+            blocks("\n\na\n\n \n\t\nb\nc\n\n\n"),
+            [vec!["a"], vec!["b", "c"]]
+        );
+        assert_eq!(blocks("a"), [vec!["a"]]);
+        assert!(blocks("").is_empty());
+        assert!(blocks("\n\r\n\n").is_empty());
+    }
+
+    #[test]
+    fn sections() {
+        let (a, b): (Vec<u32>, Vec<u32>) = paragraphs("1\n2\n\n 3 \n\n".as_bytes())
+            .sections2()
+            .unwrap();
+        assert_eq!((a, b), (vec![1, 2], vec![3]));
+
+        let (a, b, c): (Vec<i8>, Vec<i8>, Vec<i8>) = paragraphs("1\r\n\r\n-2\r\n\r\n3".as_bytes())
+            .sections3()
+            .unwrap();
+        assert_eq!((a, b, c), (vec![1], vec![-2], vec![3]));
+    }
+
+    #[test]
+    fn section_errors() {
+        let err = paragraphs("1\n\n2\nx\n".as_bytes())
+            .sections2::<Vec<u32>, Vec<u32>>()
+            .unwrap_err();
+        assert_eq!(err.section, 2);
+        assert!(err.to_string().starts_with("section 2: line 2: "));
+
+        let err = paragraphs("1\n".as_bytes()).sections2::<Vec<u32>, Vec<u32>>();
+        assert_eq!(
+            err.unwrap_err(),
+            SectionError {
+                section: 2,
+                message: String::from("missing")
+            }
+        );
+
+        let err = paragraphs("1\n\n2\n\n3\n\n4\n".as_bytes()).sections2::<Vec<u32>, Vec<u32>>();
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "section 2: 2 unexpected sections after the last one"
+        );
+
+        let err = paragraphs("1\n\n2\n".as_bytes()).sections3::<Vec<u32>, Vec<u32>, Vec<u32>>();
+        assert_eq!(err.unwrap_err().to_string(), "section 3: missing");
+
+        let err =
+            paragraphs("1\n\n2\n\n3\n\n4".as_bytes()).sections3::<Vec<u32>, Vec<u32>, Vec<u32>>();
+        assert_eq!(
+            err.unwrap_err(),
+            SectionError {
+                section: 3,
+                message: String::from("1 unexpected sections after the last one")
+            }
+        );
+    }
+}
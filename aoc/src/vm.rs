@@ -0,0 +1,343 @@
+//! A small framework for the register machines that keep showing up in the puzzles
+
+use std::{
+    collections::HashSet,
+    fmt::{self, Write},
+    ops::{Index, IndexMut},
+};
+
+pub type Value = i64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Registers<const N: usize> {
+    values: [Value; N],
+}
+
+impl<const N: usize> Default for Registers<N> {
+    fn default() -> Self {
+        Self { values: [0; N] }
+    }
+}
+
+impl<const N: usize> From<[Value; N]> for Registers<N> {
+    fn from(values: [Value; N]) -> Self {
+        Self { values }
+    }
+}
+
+impl<const N: usize> Index<usize> for Registers<N> {
+    type Output = Value;
+
+    fn index(&self, register: usize) -> &Value {
+        &self.values[register]
+    }
+}
+
+impl<const N: usize> IndexMut<usize> for Registers<N> {
+    fn index_mut(&mut self, register: usize) -> &mut Value {
+        &mut self.values[register]
+    }
+}
+
+/// What the machine should do after an instruction has been executed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Next,
+    /// relative to the current instruction
+    Jump(isize),
+    Halt,
+}
+
+pub trait Instruction<const N: usize>: fmt::Debug {
+    /// how many cycles it takes until the instruction has finished
+    fn cycles(&self) -> usize;
+
+    /// apply the effect of the instruction after its last cycle
+    fn execute(&self, registers: &mut Registers<N>) -> Flow;
+}
+
+/// Gets called during every cycle, before the current instruction has taken effect
+pub trait Observer<const N: usize> {
+    fn on_cycle(&mut self, cycle: usize, registers: &Registers<N>);
+}
+
+impl<const N: usize, F: FnMut(usize, &Registers<N>)> Observer<N> for F {
+    fn on_cycle(&mut self, cycle: usize, registers: &Registers<N>) {
+        self(cycle, registers)
+    }
+}
+
+/// notify two observers at once
+impl<const N: usize, A: Observer<N>, B: Observer<N>> Observer<N> for (A, B) {
+    fn on_cycle(&mut self, cycle: usize, registers: &Registers<N>) {
+        self.0.on_cycle(cycle, registers);
+        self.1.on_cycle(cycle, registers);
+    }
+}
+
+/// Why the machine stopped running
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// a `Flow::Halt` or the program counter left the program
+    Halted,
+    /// about to execute the instruction at this position
+    Breakpoint(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct TraceEntry<const N: usize> {
+    /// the cycle during which the instruction finished
+    pub cycle: usize,
+    pub pc: usize,
+    /// register values after the instruction has been executed
+    pub registers: Registers<N>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Machine<I, const N: usize> {
+    program: Vec<I>,
+    registers: Registers<N>,
+    pc: usize,
+    cycle: usize,
+    halted: bool,
+    breakpoints: HashSet<usize>,
+    /// set after stopping at a breakpoint, so that we can continue from there
+    resume: bool,
+    trace: Option<Vec<TraceEntry<N>>>,
+}
+
+impl<I: Instruction<N>, const N: usize> Machine<I, N> {
+    pub fn new(program: Vec<I>, registers: Registers<N>) -> Self {
+        Self {
+            program,
+            registers,
+            pc: 0,
+            cycle: 0,
+            halted: false,
+            breakpoints: HashSet::new(),
+            resume: false,
+            trace: None,
+        }
+    }
+
+    pub fn registers(&self) -> &Registers<N> {
+        &self.registers
+    }
+
+    pub fn registers_mut(&mut self) -> &mut Registers<N> {
+        &mut self.registers
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// number of cycles that have passed so far
+    pub fn cycle(&self) -> usize {
+        self.cycle
+    }
+
+    pub fn add_breakpoint(&mut self, pc: usize) {
+        self.breakpoints.insert(pc);
+    }
+
+    pub fn remove_breakpoint(&mut self, pc: usize) {
+        self.breakpoints.remove(&pc);
+    }
+
+    /// start recording every executed instruction
+    pub fn enable_trace(&mut self) {
+        self.trace.get_or_insert_with(Vec::new);
+    }
+
+    pub fn trace(&self) -> &[TraceEntry<N>] {
+        self.trace.as_deref().unwrap_or_default()
+    }
+
+    /// one line per executed instruction
+    pub fn dump_trace(&self) -> String {
+        let mut result = String::new();
+        for entry in self.trace() {
+            writeln!(
+                result,
+                "{:>6} {:>4} {:?} {:?}",
+                entry.cycle, entry.pc, self.program[entry.pc], entry.registers.values
+            )
+            .unwrap();
+        }
+        result
+    }
+
+    /// execute a single instruction
+    pub fn step<O: Observer<N>>(&mut self, observer: &mut O) -> Option<Stop> {
+        if self.halted {
+            return Some(Stop::Halted);
+        }
+        let Some(instruction) = self.program.get(self.pc) else {
+            self.halted = true;
+            return Some(Stop::Halted);
+        };
+        if !self.resume && self.breakpoints.contains(&self.pc) {
+            self.resume = true;
+            return Some(Stop::Breakpoint(self.pc));
+        }
+        self.resume = false;
+
+        for _ in 0..instruction.cycles() {
+            self.cycle += 1;
+            observer.on_cycle(self.cycle, &self.registers);
+        }
+        let flow = instruction.execute(&mut self.registers);
+        if let Some(trace) = &mut self.trace {
+            trace.push(TraceEntry {
+                cycle: self.cycle,
+                pc: self.pc,
+                registers: self.registers,
+            });
+        }
+
+        match flow {
+            Flow::Next => self.pc += 1,
+            Flow::Jump(offset) => match self.pc.checked_add_signed(offset) {
+                Some(pc) => self.pc = pc,
+                None => self.halted = true,
+            },
+            Flow::Halt => self.halted = true,
+        }
+        if self.halted {
+            Some(Stop::Halted)
+        } else {
+            None
+        }
+    }
+
+    /// run until the program halts or a breakpoint is hit. Calling it again after a breakpoint
+    /// continues the execution.
+    pub fn run<O: Observer<N>>(&mut self, observer: &mut O) -> Stop {
+        loop {
+            if let Some(stop) = self.step(observer) {
+                return stop;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    enum Op {
+        Inc(usize),
+        /// jump if the register is not zero
+        Jnz(usize, isize),
+        Dec(usize),
+        Halt,
+    }
+
+    impl Instruction<2> for Op {
+        fn cycles(&self) -> usize {
+            match self {
+                Op::Jnz(..) => 2,
+                _ => 1,
+            }
+        }
+
+        fn execute(&self, registers: &mut Registers<2>) -> Flow {
+            match *self {
+                Op::Inc(r) => registers[r] += 1,
+                Op::Dec(r) => registers[r] -= 1,
+                Op::Jnz(r, offset) if registers[r] != 0 => return Flow::Jump(offset),
+                Op::Jnz(..) => {}
+                Op::Halt => return Flow::Halt,
+            }
+            Flow::Next
+        }
+    }
+
+    /// moves register 0 into register 1
+    fn countdown(start: Value) -> Machine<Op, 2> {
+        let program = vec![Op::Dec(0), Op::Inc(1), Op::Jnz(0, -2), Op::Halt];
+        Machine::new(program, Registers::from([start, 0]))
+    }
+
+    #[test]
+    fn run_until_halt() {
+        let mut machine = countdown(3);
+        let mut cycles = vec![];
+        let stop = machine.run(&mut |cycle, _: &Registers<2>| cycles.push(cycle));
+        assert_eq!(stop, Stop::Halted);
+        assert_eq!(*machine.registers(), Registers::from([0, 3]));
+        assert_eq!(machine.pc(), 3);
+        // 3 iterations of 4 cycles and one for the halt
+        assert_eq!(machine.cycle(), 13);
+        assert_eq!(cycles, (1..=13).collect::<Vec<_>>());
+        // a halted machine stays halted
+        assert_eq!(
+            machine.step(&mut |_, _: &Registers<2>| panic!()),
+            Some(Stop::Halted)
+        );
+    }
+
+    #[test]
+    fn stop_at_breakpoint_and_resume() {
+        let mut machine = countdown(3);
+        machine.add_breakpoint(1);
+        let mut observer = |_, _: &Registers<2>| {};
+        assert_eq!(machine.run(&mut observer), Stop::Breakpoint(1));
+        // the instruction at the breakpoint hasn't been executed yet
+        assert_eq!(*machine.registers(), Registers::from([2, 0]));
+        assert_eq!(machine.run(&mut observer), Stop::Breakpoint(1));
+        assert_eq!(*machine.registers(), Registers::from([1, 1]));
+
+        machine.registers_mut()[0] = 5;
+        machine.remove_breakpoint(1);
+        assert_eq!(machine.run(&mut observer), Stop::Halted);
+        assert_eq!(*machine.registers(), Registers::from([0, 7]));
+    }
+
+    #[test]
+    fn jumps_out_of_the_program() {
+        let mut observer = |_, _: &Registers<2>| {};
+        // before the first instruction
+        let mut machine = Machine::new(vec![Op::Inc(0), Op::Jnz(0, -2)], Registers::default());
+        assert_eq!(machine.run(&mut observer), Stop::Halted);
+        assert_eq!(machine.registers()[0], 1);
+        assert_eq!(machine.pc(), 1);
+
+        // past the last instruction
+        let mut machine = Machine::new(
+            vec![Op::Inc(0), Op::Jnz(0, 5), Op::Inc(1)],
+            Registers::default(),
+        );
+        assert_eq!(machine.run(&mut observer), Stop::Halted);
+        assert_eq!(*machine.registers(), Registers::from([1, 0]));
+        assert_eq!(machine.pc(), 6);
+        assert_eq!(machine.step(&mut observer), Some(Stop::Halted));
+    }
+
+    #[test]
+    fn trace() {
+        let mut machine = countdown(2);
+        machine.run(&mut |_, _: &Registers<2>| {});
+        assert!(machine.trace().is_empty());
+        assert_eq!(machine.dump_trace(), "");
+
+        let mut machine = countdown(1);
+        machine.enable_trace();
+        machine.run(&mut |_, _: &Registers<2>| {});
+        let pcs: Vec<usize> = machine.trace().iter().map(|entry| entry.pc).collect();
+        assert_eq!(pcs, [0, 1, 2, 3]);
+        let dump = machine.dump_trace();
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "     1    0 Dec(0) [0, 0]",
+                "     2    1 Inc(1) [0, 1]",
+                "     4    2 Jnz(0, -2) [0, 1]",
+                "     5    3 Halt [0, 1]",
+            ]
+        );
+    }
+}